@@ -0,0 +1,224 @@
+pub mod beam;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use crate::engine::piece::{Kind, Piece};
+use crate::engine::placement::{Input, Placement};
+use crate::engine::{reachable_placements, Board, Engine};
+use crate::input::{Frame, InputSource};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Weights {
+    pub height: f64,
+    pub holes: f64,
+    pub bumpiness: f64,
+    pub wells: f64,
+    pub lines: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            height: -0.51,
+            holes: -0.36,
+            bumpiness: -0.18,
+            wells: -0.1,
+            lines: 0.76,
+        }
+    }
+}
+
+impl Weights {
+    pub const NAMES: [&'static str; 5] = ["height", "holes", "bumpiness", "wells", "lines"];
+
+    pub fn to_array(&self) -> [f64; 5] {
+        [
+            self.height,
+            self.holes,
+            self.bumpiness,
+            self.wells,
+            self.lines,
+        ]
+    }
+
+    pub fn from_array(values: [f64; 5]) -> Self {
+        let [height, holes, bumpiness, wells, lines] = values;
+        Weights {
+            height,
+            holes,
+            bumpiness,
+            wells,
+            lines,
+        }
+    }
+
+    // One "name = value" per line; anything not given keeps its default.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut values = Weights::default().to_array();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or(format!("{}: expected name = value", line))?;
+            let index = Weights::NAMES
+                .iter()
+                .position(|known| *known == name.trim())
+                .ok_or(format!("unknown weight {:?}", name.trim()))?;
+            values[index] = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{}: not a number", line))?;
+        }
+        Ok(Weights::from_array(values))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text: String = Weights::NAMES
+            .iter()
+            .zip(self.to_array())
+            .map(|(name, value)| format!("{} = {}\n", name, value))
+            .collect();
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+pub fn evaluate(board: &Board, lines: usize, weights: &Weights) -> f64 {
+    let heights = board.column_heights();
+    weights.height * heights.aggregate() as f64
+        + weights.holes * board.holes() as f64
+        + weights.bumpiness * heights.bumpiness() as f64
+        + weights.wells * heights.well_depths().iter().sum::<isize>() as f64
+        + weights.lines * lines as f64
+}
+
+// Picks the placement for the piece that leaves the best board once the
+// next piece, if known, has been placed as well as it can be.
+pub fn choose(
+    board: &Board,
+    piece: &Piece,
+    next: Option<Kind>,
+    weights: &Weights,
+) -> Option<Placement> {
+    let mut best: Option<(f64, Placement)> = None;
+    for placement in reachable_placements(board, piece) {
+        let mut after = board.clone();
+        let lines = match after.place(&placement.piece) {
+            Ok(lines) => lines,
+            Err(_) => continue,
+        };
+        let score = match next {
+            None => evaluate(&after, lines, weights),
+            Some(kind) => reachable_placements(&after, &Piece::spawn(kind))
+                .iter()
+                .filter_map(|second| {
+                    let mut last = after.clone();
+                    let more = last.place(&second.piece).ok()?;
+                    Some(evaluate(&last, lines + more, weights))
+                })
+                .fold(f64::NEG_INFINITY, f64::max),
+        };
+        if best.as_ref().is_none_or(|(top, _)| score > *top) {
+            best = Some((score, placement));
+        }
+    }
+    best.map(|(_, placement)| placement)
+}
+
+pub trait Planner {
+    fn plan(&mut self, engine: &Engine) -> Option<Placement>;
+}
+
+// One piece of lookahead into the queue, see choose().
+pub struct Greedy {
+    pub weights: Weights,
+}
+
+impl Planner for Greedy {
+    fn plan(&mut self, engine: &Engine) -> Option<Placement> {
+        let cursor = engine.cursor.as_ref()?;
+        choose(
+            engine.board(),
+            cursor,
+            engine.queue.front().copied(),
+            &self.weights,
+        )
+    }
+}
+
+// Plays the engine through the same inputs a person would use: moves and
+// rotations are tapped, soft drop is held until the piece has come down the
+// planned number of rows, and every piece ends with a hard drop.
+pub struct Ai {
+    planner: Box<dyn Planner + Send>,
+    plan: VecDeque<Input>,
+    handled: Option<usize>,
+    target_y: isize,
+    dropping: bool,
+}
+
+impl Ai {
+    pub fn new(weights: Weights) -> Self {
+        Self::with_planner(Box::new(Greedy { weights }))
+    }
+
+    pub fn with_planner(planner: Box<dyn Planner + Send>) -> Self {
+        Ai {
+            planner,
+            plan: VecDeque::new(),
+            handled: None,
+            target_y: 0,
+            dropping: false,
+        }
+    }
+}
+
+impl Default for Ai {
+    fn default() -> Self {
+        Self::new(Weights::default())
+    }
+}
+
+impl InputSource for Ai {
+    fn frame(&mut self, engine: &Engine) -> Frame {
+        let mut frame = Frame::default();
+        let cursor = match &engine.cursor {
+            Some(c) => c,
+            None => return frame,
+        };
+        if self.handled != Some(engine.pieces) {
+            self.handled = Some(engine.pieces);
+            self.target_y = cursor.position.y;
+            self.dropping = false;
+            self.plan = self
+                .planner
+                .plan(engine)
+                .map_or_else(|| VecDeque::from([Input::HardDrop]), |p| p.inputs.into());
+        }
+
+        while let Some(input) = self.plan.front().copied() {
+            match input {
+                Input::Move(direction) => frame.moves.push(direction),
+                Input::SoftDrop => {
+                    if !self.dropping {
+                        self.dropping = true;
+                        self.target_y += 1;
+                    }
+                    if cursor.position.y < self.target_y {
+                        frame.soft_drop = true;
+                        break;
+                    }
+                    self.dropping = false;
+                }
+                Input::HardDrop => frame.hard_drop = true,
+            }
+            self.plan.pop_front();
+        }
+        frame
+    }
+}
@@ -0,0 +1,250 @@
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::{evaluate, Planner, Weights};
+use crate::engine::piece::{Kind, Piece};
+use crate::engine::placement::Placement;
+use crate::engine::{reachable_placements, Board, Engine};
+
+#[derive(Clone, Copy, Debug)]
+pub struct BeamConfig {
+    // Boards kept between plies.
+    pub width: usize,
+    // Pieces searched, the active one included; capped by the queue.
+    pub depth: usize,
+    pub max_nodes: Option<usize>,
+    pub max_time: Option<Duration>,
+    // Chance of playing one of the runner-up moves instead of the best.
+    pub mistake_rate: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 4] = [Self::Easy, Self::Medium, Self::Hard, Self::Expert];
+
+    pub fn config(&self) -> BeamConfig {
+        match self {
+            Difficulty::Easy => BeamConfig {
+                width: 1,
+                depth: 1,
+                max_nodes: None,
+                max_time: None,
+                mistake_rate: 0.25,
+            },
+            Difficulty::Medium => BeamConfig {
+                width: 4,
+                depth: 2,
+                max_nodes: Some(2_000),
+                max_time: None,
+                mistake_rate: 0.08,
+            },
+            Difficulty::Hard => BeamConfig {
+                width: 8,
+                depth: 3,
+                max_nodes: Some(4_000),
+                max_time: None,
+                mistake_rate: 0.02,
+            },
+            Difficulty::Expert => BeamConfig {
+                width: 24,
+                depth: 4,
+                max_nodes: Some(12_000),
+                max_time: None,
+                mistake_rate: 0.0,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|d| format!("{:?}", d).eq_ignore_ascii_case(s))
+            .ok_or(format!("unknown difficulty {:?}", s))
+    }
+}
+
+struct Node {
+    board: Board,
+    lines: usize,
+    score: f64,
+    // Index of the first move of the line this board came from.
+    root: usize,
+}
+
+// Keeps the best few boards after every piece of the queue and plays the
+// first move of the line leading to the best one.
+pub struct Beam {
+    pub weights: Weights,
+    pub config: BeamConfig,
+    rng: ChaCha8Rng,
+}
+
+impl Beam {
+    pub fn new(weights: Weights, config: BeamConfig, seed: u64) -> Self {
+        Beam {
+            weights,
+            config,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn search(&mut self, board: &Board, piece: &Piece, queue: &[Kind]) -> Option<Placement> {
+        let started = Instant::now();
+        let mut nodes = 0;
+        let roots = reachable_placements(board, piece);
+        let mut beam: Vec<Node> = roots
+            .iter()
+            .enumerate()
+            .filter_map(|(root, placement)| self.child(board, 0, placement, root))
+            .collect();
+        nodes += beam.len();
+        beam.sort_by(|a, b| b.score.total_cmp(&a.score));
+        // First moves from best to worst, for when we blunder.
+        let ranked: Vec<usize> = beam.iter().map(|node| node.root).collect();
+        self.prune(&mut beam);
+
+        let depth = self.config.depth.min(queue.len() + 1);
+        for kind in queue.iter().take(depth.saturating_sub(1)) {
+            let mut next: Vec<Node> = vec![];
+            let mut exhausted = false;
+            // Checked board by board, as a single ply of a wide beam can take
+            // far more than the whole budget. The beam is best first, so a
+            // ply cut short has still looked past the most promising boards.
+            for node in &beam {
+                if self.out_of_budget(started, nodes) {
+                    exhausted = true;
+                    break;
+                }
+                for placement in reachable_placements(&node.board, &Piece::spawn(*kind)) {
+                    if let Some(child) = self.child(&node.board, node.lines, &placement, node.root)
+                    {
+                        next.push(child);
+                        nodes += 1;
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            self.prune(&mut next);
+            beam = next;
+            if exhausted {
+                break;
+            }
+        }
+
+        let best = beam.first()?.root;
+        let choice = if ranked.len() > 1 && self.rng.gen_bool(self.config.mistake_rate) {
+            let runner_ups = &ranked[1..ranked.len().min(5)];
+            runner_ups[self.rng.gen_range(0..runner_ups.len())]
+        } else {
+            best
+        };
+        roots.into_iter().nth(choice)
+    }
+
+    fn child(
+        &self,
+        board: &Board,
+        lines: usize,
+        placement: &Placement,
+        root: usize,
+    ) -> Option<Node> {
+        let mut after = board.clone();
+        let lines = lines + after.place(&placement.piece).ok()?;
+        Some(Node {
+            score: evaluate(&after, lines, &self.weights),
+            board: after,
+            lines,
+            root,
+        })
+    }
+
+    fn prune(&self, beam: &mut Vec<Node>) {
+        beam.sort_by(|a, b| b.score.total_cmp(&a.score));
+        beam.truncate(self.config.width.max(1));
+    }
+
+    fn out_of_budget(&self, started: Instant, nodes: usize) -> bool {
+        self.config.max_nodes.is_some_and(|max| nodes >= max)
+            || self
+                .config
+                .max_time
+                .is_some_and(|max| started.elapsed() >= max)
+    }
+}
+
+impl Planner for Beam {
+    fn plan(&mut self, engine: &Engine) -> Option<Placement> {
+        let cursor = engine.cursor.as_ref()?;
+        let queue: Vec<Kind> = engine.queue.iter().copied().collect();
+        self.search(engine.board(), cursor, &queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Ai;
+    use crate::sim::{self, GameResult, Limits};
+
+    const LIMITS: Limits = Limits {
+        max_pieces: Some(25),
+        max_time: None,
+    };
+
+    fn config(depth: usize, max_nodes: Option<usize>, mistake_rate: f64) -> BeamConfig {
+        BeamConfig {
+            width: 2,
+            depth,
+            max_nodes,
+            max_time: None,
+            mistake_rate,
+        }
+    }
+
+    fn game(config: BeamConfig, seed: u64) -> GameResult {
+        let beam = Beam::new(Weights::default(), config, seed);
+        sim::run(3, &mut Ai::with_planner(Box::new(beam)), LIMITS)
+    }
+
+    fn outcome(result: GameResult) -> (usize, usize, usize, bool) {
+        (
+            result.pieces,
+            result.lines,
+            result.points,
+            result.topped_out,
+        )
+    }
+
+    // With no nodes to spare after the first piece the search never looks
+    // into the queue, so it plays just like a search one piece deep.
+    #[test]
+    fn node_budget_cuts_the_search_short() {
+        let shallow = outcome(game(config(1, None, 0.0), 0));
+        let deep = outcome(game(config(3, None, 0.0), 0));
+        let starved = outcome(game(config(3, Some(1), 0.0), 0));
+        assert_ne!(deep, shallow);
+        assert_eq!(starved, shallow);
+    }
+
+    #[test]
+    fn no_mistakes_plays_the_same_whatever_the_seed() {
+        let first = outcome(game(config(2, None, 0.0), 1));
+        assert_eq!(outcome(game(config(2, None, 0.0), 1)), first);
+        assert_eq!(outcome(game(config(2, None, 0.0), 2)), first);
+    }
+}
@@ -0,0 +1,155 @@
+use std::{env, fs, path::Path, process, time::Duration};
+
+use tetris::ai::beam::{Beam, Difficulty};
+use tetris::ai::{Ai, Weights};
+use tetris::engine::{ClearGravity, ClearStyle, Engine, Progression, SoftDrop, Timing};
+use tetris::input::{InputSource, Replay};
+use tetris::sim::{self, Limits, RandomMoves, Script};
+use tetris::tbp::TbpBot;
+
+const USAGE: &str = "usage: tetris-sim [--games N] [--seed N] [--pieces N] [--seconds N] [--timing modern|classic|instant|ARE/CLEAR/LOCK] [--clear simultaneous|sequential] [--gravity naive|sticky|cascade] [--level N] [--progression classic|guideline] [--script FILE | --ai | --beam LEVEL | --replay FILE] [--weights FILE] [--tbp COMMAND]";
+
+struct Options {
+    games: u64,
+    seed: u64,
+    limits: Limits,
+    script: Option<String>,
+    ai: bool,
+    beam: Option<Difficulty>,
+    weights: Weights,
+    tbp: Option<String>,
+    replay: Option<String>,
+    soft_drop: SoftDrop,
+    timing: Timing,
+    clear_style: ClearStyle,
+    clear_gravity: ClearGravity,
+    start_level: usize,
+    progression: Progression,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        games: 1,
+        seed: 0,
+        limits: Limits {
+            max_pieces: Some(1000),
+            max_time: None,
+        },
+        script: None,
+        ai: false,
+        beam: None,
+        weights: Weights::default(),
+        tbp: None,
+        replay: None,
+        soft_drop: SoftDrop::default(),
+        timing: Timing::default(),
+        clear_style: ClearStyle::default(),
+        clear_gravity: ClearGravity::default(),
+        start_level: 1,
+        progression: Progression::default(),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--games" => options.games = number(&value()?)?,
+            "--seed" => options.seed = number(&value()?)?,
+            "--pieces" => options.limits.max_pieces = Some(number(&value()?)? as usize),
+            "--seconds" => options.limits.max_time = Some(Duration::from_secs(number(&value()?)?)),
+            "--timing" => options.timing = value()?.parse()?,
+            "--clear" => options.clear_style = value()?.parse()?,
+            "--gravity" => options.clear_gravity = value()?.parse()?,
+            "--level" => options.start_level = number(&value()?)? as usize,
+            "--progression" => options.progression = value()?.parse()?,
+            "--script" => options.script = Some(value()?),
+            "--ai" => options.ai = true,
+            "--beam" => options.beam = Some(value()?.parse()?),
+            "--tbp" => options.tbp = Some(value()?),
+            "--replay" => options.replay = Some(value()?),
+            "--weights" => options.weights = Weights::load(Path::new(&value()?))?,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("unknown argument {:?}\n{}", arg, USAGE)),
+        }
+    }
+    // A replay is one game on its own seed, run for as long as it was played.
+    if let Some(path) = &options.replay {
+        let replay = Replay::load(Path::new(path))?;
+        options.games = 1;
+        options.seed = replay.seed;
+        options.soft_drop = replay.soft_drop;
+        options.timing = replay.timing;
+        options.clear_style = replay.clear_style;
+        options.clear_gravity = replay.clear_gravity;
+        options.start_level = replay.start_level;
+        options.progression = replay.progression;
+        options.limits = Limits {
+            max_pieces: None,
+            max_time: Some(replay.duration()),
+        };
+    }
+    Ok(options)
+}
+
+fn number(s: &str) -> Result<u64, String> {
+    s.parse().map_err(|_| format!("not a number: {:?}", s))
+}
+
+fn source(options: &Options, seed: u64) -> Result<Box<dyn InputSource>, String> {
+    if let Some(command) = &options.tbp {
+        let bot = TbpBot::spawn(command)?;
+        eprintln!("playing with {}", bot.name);
+        return Ok(Box::new(Ai::with_planner(Box::new(bot))));
+    }
+    if let Some(path) = &options.replay {
+        return Ok(Box::new(Replay::load(Path::new(path))?));
+    }
+    if let Some(difficulty) = options.beam {
+        let beam = Beam::new(options.weights, difficulty.config(), seed);
+        return Ok(Box::new(Ai::with_planner(Box::new(beam))));
+    }
+    if options.ai {
+        return Ok(Box::new(Ai::new(options.weights)));
+    }
+    match &options.script {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            Ok(Box::new(Script::parse(&text)?))
+        }
+        None => Ok(Box::new(RandomMoves::new(seed))),
+    }
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    let mut total_lines = 0;
+    let mut total_points = 0;
+    for seed in options.seed..options.seed + options.games {
+        let mut source = source(&options, seed).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+        let mut engine = Engine::simulated(seed);
+        engine.soft_drop = options.soft_drop;
+        engine.timing = options.timing;
+        engine.clear_style = options.clear_style;
+        engine.clear_gravity = options.clear_gravity;
+        engine.progression = options.progression;
+        engine.set_start_level(options.start_level);
+        let result = sim::play(engine, seed, source.as_mut(), options.limits);
+        total_lines += result.lines;
+        total_points += result.points;
+        println!("{}", result);
+    }
+    if options.games > 1 {
+        println!(
+            "games={} mean_lines={:.1} mean_score={:.1}",
+            options.games,
+            total_lines as f64 / options.games as f64,
+            total_points as f64 / options.games as f64
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{env, path::PathBuf, process, thread};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tetris::ai::{Ai, Weights};
+use tetris::sim::{self, Limits};
+
+const USAGE: &str = "usage: tetris-tune [--generations N] [--population N] [--games N] \
+[--pieces N] [--seed N] [--threads N] [--out FILE]";
+
+struct Options {
+    generations: usize,
+    population: usize,
+    games: u64,
+    pieces: usize,
+    seed: u64,
+    threads: usize,
+    out: PathBuf,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        generations: 10,
+        population: 32,
+        games: 4,
+        pieces: 300,
+        seed: 0,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        out: PathBuf::from("weights.cfg"),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--generations" => options.generations = number(&value()?)? as usize,
+            "--population" => options.population = number(&value()?)?.max(2) as usize,
+            "--games" => options.games = number(&value()?)?.max(1),
+            "--pieces" => options.pieces = number(&value()?)? as usize,
+            "--seed" => options.seed = number(&value()?)?,
+            "--threads" => options.threads = number(&value()?)?.max(1) as usize,
+            "--out" => options.out = PathBuf::from(value()?),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("unknown argument {:?}\n{}", arg, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+fn number(s: &str) -> Result<u64, String> {
+    s.parse().map_err(|_| format!("not a number: {:?}", s))
+}
+
+// Mean lines cleared over the same seeded games for every candidate, so
+// they are compared on equal footing within a generation. Candidates play
+// with the same planner as the in-game AI so the weights suit it.
+fn fitness(weights: Weights, seeds: &[u64], pieces: usize) -> f64 {
+    let limits = Limits {
+        max_pieces: Some(pieces),
+        max_time: None,
+    };
+    let total: usize = seeds
+        .iter()
+        .map(|seed| {
+            let mut ai = Ai::new(weights);
+            sim::run(*seed, &mut ai, limits).lines
+        })
+        .sum();
+    total as f64 / seeds.len() as f64
+}
+
+fn score_all(candidates: &[Weights], seeds: &[u64], options: &Options) -> Vec<f64> {
+    let chunk = candidates.len().div_ceil(options.threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk)
+            .map(|group| {
+                scope.spawn(move || {
+                    group
+                        .iter()
+                        .map(|weights| fitness(*weights, seeds, options.pieces))
+                        .collect::<Vec<f64>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+fn gaussian(rng: &mut ChaCha8Rng) -> f64 {
+    // Box-Muller
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    // Cross-entropy method: sample around the mean, refit to the elite.
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let mut mean = Weights::default().to_array();
+    let mut deviation = [0.5; 5];
+    let elite = (options.population / 4).max(1);
+    let mut best: Option<(f64, Weights)> = None;
+
+    for generation in 0..options.generations {
+        let first_seed = options.seed + (generation as u64) * options.games;
+        let seeds: Vec<u64> = (first_seed..first_seed + options.games).collect();
+        // Scores on different games don't compare, so the best so far plays
+        // this generation's games too.
+        if let Some((_, weights)) = best {
+            best = Some((fitness(weights, &seeds, options.pieces), weights));
+        }
+        let candidates: Vec<Weights> = (0..options.population)
+            .map(|_| {
+                let mut values = mean;
+                for (value, sd) in values.iter_mut().zip(deviation) {
+                    *value += sd * gaussian(&mut rng);
+                }
+                Weights::from_array(values)
+            })
+            .collect();
+
+        let scores = score_all(&candidates, &seeds, &options);
+        let mut ranked: Vec<(f64, Weights)> = scores.into_iter().zip(candidates).collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        for i in 0..mean.len() {
+            let values: Vec<f64> = ranked[..elite]
+                .iter()
+                .map(|(_, w)| w.to_array()[i])
+                .collect();
+            mean[i] = values.iter().sum::<f64>() / elite as f64;
+            let variance = values.iter().map(|v| (v - mean[i]).powi(2)).sum::<f64>() / elite as f64;
+            // Some noise keeps the search from collapsing too early.
+            deviation[i] = variance.sqrt() + 0.05 / (generation + 1) as f64;
+        }
+
+        let (score, weights) = ranked[0];
+        println!(
+            "generation={} best_lines={:.1} elite_mean_lines={:.1} weights={:?}",
+            generation,
+            score,
+            ranked[..elite].iter().map(|(s, _)| s).sum::<f64>() / elite as f64,
+            weights.to_array()
+        );
+        if best.is_none_or(|(top, _)| score > top) {
+            best = Some((score, weights));
+        }
+    }
+
+    if let Some((score, weights)) = best {
+        if let Err(e) = weights.save(&options.out) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        println!(
+            "best_lines={:.1} written to {}",
+            score,
+            options.out.display()
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+use serde::Serialize;
+use tetris::engine::piece::{Direction, Kind};
+use tetris::engine::{Board, Coordinate, Engine};
+use tetris::input::{Frame, InputSource};
+
+// Line based remote control for a running game: clients send one command
+// per line and, once subscribed, get a JSON snapshot of the game per frame.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Move(Direction),
+    HardDrop,
+    SoftDrop(bool),
+    Pause,
+}
+
+#[derive(Serialize)]
+pub struct Snapshot<'a> {
+    pub state: &'a str,
+    pub level: usize,
+    pub score: usize,
+    pub lines: usize,
+    pub pieces: usize,
+    // Top row first, '.' for empty and the piece letter otherwise.
+    pub board: Vec<String>,
+    pub active: Option<Kind>,
+    pub active_cells: Vec<(isize, isize)>,
+    pub queue: Vec<Kind>,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(state: &'a str, engine: &Engine) -> Self {
+        let board = (0..Board::HEIGHT)
+            .map(|y| {
+                (0..Board::WIDTH)
+                    .map(|x| {
+                        let coord = Coordinate::new(x, y);
+                        match (engine.board().filled(coord), engine.board().kind(coord)) {
+                            (false, _) => '.',
+                            (true, Some(kind)) => format!("{}", kind).chars().next().unwrap(),
+                            (true, None) => 'G',
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Snapshot {
+            state,
+            level: engine.level,
+            score: engine.points,
+            lines: engine.rows_cleared,
+            pieces: engine.pieces,
+            board,
+            active: engine.cursor.as_ref().map(|c| c.kind),
+            active_cells: engine
+                .cursor
+                .as_ref()
+                .map_or(vec![], |c| c.cells().iter().map(|m| (m.x, m.y)).collect()),
+            queue: engine.queue.iter().copied().collect(),
+        }
+    }
+}
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+struct Client {
+    stream: Box<dyn Stream>,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    subscribed: bool,
+    closed: bool,
+}
+
+impl Client {
+    // Slow readers are dropped rather than allowed to stall the game.
+    const MAX_BACKLOG: usize = 1 << 20;
+    // Nor may a client send without end and never finish a line.
+    const MAX_INCOMING: usize = 1 << 16;
+
+    fn queue(&mut self, line: &str) {
+        self.outgoing.extend_from_slice(line.as_bytes());
+        self.outgoing.push(b'\n');
+        if self.outgoing.len() > Client::MAX_BACKLOG {
+            self.closed = true;
+        }
+    }
+
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() && !self.closed {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+
+    fn read_lines(&mut self) -> Vec<String> {
+        let mut buffer = [0u8; 512];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.incoming.extend_from_slice(&buffer[..n]);
+                    if self.incoming.len() > Client::MAX_INCOMING {
+                        self.closed = true;
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        let mut lines = vec![];
+        while let Some(end) = self.incoming.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+        lines
+    }
+}
+
+pub struct ControlServer {
+    listener: Listener,
+    clients: Vec<Client>,
+    soft_drop: bool,
+}
+
+impl ControlServer {
+    // Either host:port, or unix:PATH for a Unix domain socket.
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let listener = match address.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) => {
+                // Left behind by an earlier run; anything else at the path
+                // isn't ours to remove.
+                if let Ok(metadata) = std::fs::symlink_metadata(path) {
+                    if !metadata.file_type().is_socket() {
+                        return Err(format!("{}: exists and is not a socket", path));
+                    }
+                    std::fs::remove_file(path).map_err(|e| format!("{}: {}", path, e))?;
+                }
+                let listener = UnixListener::bind(path).map_err(|e| format!("{}: {}", path, e))?;
+                listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+                Listener::Unix(listener)
+            }
+            #[cfg(not(unix))]
+            Some(_) => return Err("Unix sockets are not supported here".to_string()),
+            None => {
+                let listener =
+                    TcpListener::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+                listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+                Listener::Tcp(listener)
+            }
+        };
+        Ok(ControlServer {
+            listener,
+            clients: vec![],
+            soft_drop: false,
+        })
+    }
+
+    fn accept(&mut self) {
+        loop {
+            let stream: Box<dyn Stream> = match &self.listener {
+                Listener::Tcp(listener) => match listener.accept() {
+                    Ok((stream, _)) if stream.set_nonblocking(true).is_ok() => Box::new(stream),
+                    Ok(_) => continue,
+                    Err(_) => break,
+                },
+                #[cfg(unix)]
+                Listener::Unix(listener) => match listener.accept() {
+                    Ok((stream, _)) if stream.set_nonblocking(true).is_ok() => Box::new(stream),
+                    Ok(_) => continue,
+                    Err(_) => break,
+                },
+            };
+            self.clients.push(Client {
+                stream,
+                incoming: vec![],
+                outgoing: vec![],
+                subscribed: false,
+                closed: false,
+            });
+        }
+    }
+
+    // Accepts new clients and returns the game commands sent since the
+    // last frame.
+    pub fn poll(&mut self) -> Vec<Command> {
+        self.accept();
+        let mut commands = vec![];
+        for client in self.clients.iter_mut() {
+            for line in client.read_lines() {
+                let command = match line.to_ascii_lowercase().as_str() {
+                    "" => continue,
+                    "left" => Command::Move(Direction::LEFT),
+                    "right" => Command::Move(Direction::RIGHT),
+                    "cw" => Command::Move(Direction::CW),
+                    "ccw" => Command::Move(Direction::CCW),
+                    "flip" => Command::Move(Direction::FLIP),
+                    "drop" => Command::HardDrop,
+                    "soft on" => Command::SoftDrop(true),
+                    "soft off" => Command::SoftDrop(false),
+                    "pause" => Command::Pause,
+                    "subscribe" => {
+                        client.subscribed = true;
+                        continue;
+                    }
+                    "unsubscribe" => {
+                        client.subscribed = false;
+                        continue;
+                    }
+                    _ => {
+                        let error =
+                            serde_json::json!({ "error": "unknown command", "command": line });
+                        client.queue(&error.to_string());
+                        continue;
+                    }
+                };
+                commands.push(command);
+            }
+            client.flush();
+        }
+        self.clients.retain(|client| !client.closed);
+        commands
+    }
+
+    pub fn broadcast(&mut self, snapshot: &Snapshot) {
+        if !self.clients.iter().any(|client| client.subscribed) {
+            return;
+        }
+        let line = match serde_json::to_string(snapshot) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        for client in self.clients.iter_mut().filter(|client| client.subscribed) {
+            client.queue(&line);
+            client.flush();
+        }
+        self.clients.retain(|client| !client.closed);
+    }
+}
+
+impl InputSource for ControlServer {
+    fn frame(&mut self, _engine: &Engine) -> Frame {
+        let mut frame = Frame::default();
+        for command in self.poll() {
+            match command {
+                Command::Move(direction) => frame.moves.push(direction),
+                Command::HardDrop => frame.hard_drop = true,
+                Command::SoftDrop(held) => self.soft_drop = held,
+                Command::Pause => frame.pause = true,
+            }
+        }
+        frame.soft_drop = self.soft_drop;
+        frame
+    }
+}
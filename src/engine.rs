@@ -1,20 +1,28 @@
+mod metrics;
 pub mod piece;
+pub mod placement;
 
 use cgmath::Vector2;
-use rand::{prelude::SliceRandom, prelude::ThreadRng, thread_rng};
+use rand::{prelude::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::{
     cmp,
     collections::VecDeque,
+    fmt,
+    str::FromStr,
     time::{Duration, Instant},
 };
 
-use self::piece::{Direction, Kind, Piece, Rotation};
+pub use self::metrics::Heights;
+use self::piece::{Direction, Kind, Piece, Spin};
+pub use self::placement::{perft, reachable_placements};
 
 pub type Coordinate = Vector2<isize>;
 #[derive(Clone, Copy, Debug)]
 pub struct CellData {
     pub coord: Coordinate,
-    pub kind: Kind,
+    // None for garbage.
+    pub kind: Option<Kind>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -34,6 +42,7 @@ impl Cell {
     }
 }
 
+#[derive(Clone)]
 pub struct Board([Cell; Self::SIZE as usize], Vec<isize>);
 
 impl Board {
@@ -45,7 +54,23 @@ impl Board {
         Self([Cell::new(); Self::SIZE as usize], vec![])
     }
 
-    fn filled(&self, coord: Coordinate) -> bool {
+    // Rows from top to bottom, 'X' or '#' for a filled cell, with the last
+    // row given sitting on the floor.
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let mut board = Self::blank();
+        let top = Board::HEIGHT - rows.len() as isize;
+        for (y, line) in rows.iter().enumerate() {
+            for (x, c) in line.chars().take(Board::WIDTH as usize).enumerate() {
+                if c == 'X' || c == '#' {
+                    let offset = ((top + y as isize) * Board::WIDTH + x as isize) as usize;
+                    board.0[offset].filled = true;
+                }
+            }
+        }
+        board
+    }
+
+    pub fn filled(&self, coord: Coordinate) -> bool {
         if coord.y < 0 {
             return false;
         }
@@ -58,13 +83,20 @@ impl Board {
         self.0[offset as usize].filled
     }
 
+    pub fn kind(&self, coord: Coordinate) -> Option<Kind> {
+        if !self.filled(coord) || coord.y >= Board::HEIGHT {
+            return None;
+        }
+        self.0[(coord.y * Board::WIDTH + coord.x) as usize].kind
+    }
+
     fn add(&mut self, piece: &Piece) -> Result<(), String> {
-        for cell in piece.get_cells() {
-            let offset = (cell.y + 1) * Board::WIDTH + cell.x;
+        for cell in piece.cells() {
+            let offset = cell.y * Board::WIDTH + cell.x;
             if offset < 0 {
                 return Err("Integer underflow".to_string());
             }
-            if offset > Board::SIZE {
+            if offset >= Board::SIZE {
                 continue;
             }
             self.0[offset as usize].filled = true;
@@ -73,7 +105,77 @@ impl Board {
         Ok(())
     }
 
-    fn has_patterns(&mut self, points: &mut usize, level: &mut usize) -> bool {
+    // Pushes garbage rows in under the stack, given top to bottom by the
+    // column of their hole. True if that pushed blocks out of the top.
+    pub fn add_garbage(&mut self, holes: &[isize]) -> bool {
+        let rows = cmp::min(holes.len() as isize, Board::HEIGHT);
+        let shift = (rows * Board::WIDTH) as usize;
+        let overflow = self.0[..shift].iter().any(|cell| cell.filled);
+        self.0.copy_within(shift.., 0);
+        for (i, hole) in holes[holes.len() - rows as usize..].iter().enumerate() {
+            let row = Board::HEIGHT - rows + i as isize;
+            for col in 0..Board::WIDTH {
+                let cell = &mut self.0[(row * Board::WIDTH + col) as usize];
+                *cell = Cell::new();
+                cell.filled = col != *hole;
+            }
+        }
+        for row in &mut self.1 {
+            *row -= rows;
+        }
+        overflow
+    }
+
+    // Rows with garbage left in them.
+    pub fn garbage_rows(&self) -> usize {
+        (0..Board::HEIGHT)
+            .filter(|row| {
+                let start = (row * Board::WIDTH) as usize;
+                self.0[start..start + Board::WIDTH as usize]
+                    .iter()
+                    .any(|cell| cell.filled && cell.kind.is_none())
+            })
+            .count()
+    }
+
+    // Locks the piece and collapses completed rows at once, for looking
+    // ahead without going through the engine's clearing animation.
+    pub fn place(&mut self, piece: &Piece) -> Result<usize, String> {
+        self.add(piece)?;
+        Ok(self.collapse(|cell| cell.filled))
+    }
+
+    // Removes every row whose cells all match in one pass, moving the rows
+    // above down over them.
+    fn collapse(&mut self, remove: fn(&Cell) -> bool) -> usize {
+        let mut cleared = 0;
+        for row in (0..Board::HEIGHT).rev() {
+            let start = (row * Board::WIDTH) as usize;
+            let full = self.0[start..start + Board::WIDTH as usize]
+                .iter()
+                .all(remove);
+            if full {
+                cleared += 1;
+            } else if cleared > 0 {
+                let target = start + (cleared * Board::WIDTH) as usize;
+                self.0.copy_within(start..start + Board::WIDTH as usize, target);
+            }
+        }
+        for cell in &mut self.0[..(cleared * Board::WIDTH) as usize] {
+            *cell = Cell::new();
+        }
+        cleared as usize
+    }
+
+    // Marks completed rows and scores them, a clear that gravity set off
+    // counting once more for each link of the chain.
+    fn has_patterns(
+        &mut self,
+        points: &mut usize,
+        level: &mut usize,
+        spin: Spin,
+        chain: usize,
+    ) -> bool {
         let mut found = false;
         for row in 0..Board::HEIGHT {
             let mut cells: Vec<usize> = vec![];
@@ -89,20 +191,134 @@ impl Board {
                 self.1.push(row);
             }
         }
-        match self.1.len() {
-            1 => *points += 100 * *level,
-            2 => *points += 300 * *level,
-            3 => *points += 500 * *level,
-            4 => *points += 800 * *level,
-            _ => (), // Can't happen
-        }
+        let score = match (spin, self.1.len()) {
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, 3) => 1600,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, 2) => 400,
+            (_, 1) => 100,
+            (_, 2) => 300,
+            (_, 3) => 500,
+            (_, 4) => 800,
+            // Only a cascade clears more than four rows at once.
+            (_, rows) => 200 * rows,
+        };
+        *points += score * *level * chain.max(1);
         found
     }
 
-    fn clear_marked(&mut self) -> bool {
+    fn clear_marked(&mut self) -> usize {
+        self.1.clear();
+        self.collapse(|cell| cell.marked)
+    }
+
+    // Empties the marked rows and lets what was above them fall by the
+    // given gravity, returning the rows cleared.
+    fn clear_marked_with(&mut self, gravity: ClearGravity) -> usize {
+        if gravity == ClearGravity::Naive {
+            return self.clear_marked();
+        }
+        let cleared = self.1.len();
+        self.1.clear();
+        for cell in self.0.iter_mut().filter(|cell| cell.marked) {
+            *cell = Cell::new();
+        }
+        match gravity {
+            ClearGravity::Sticky => self.settle_groups(),
+            _ => self.settle_cells(),
+        }
+        cleared
+    }
+
+    // Every block falls on its own, down to the one below it.
+    fn settle_cells(&mut self) {
+        for col in 0..Board::WIDTH {
+            let mut floor = Board::HEIGHT - 1;
+            for row in (0..Board::HEIGHT).rev() {
+                let offset = (row * Board::WIDTH + col) as usize;
+                if self.0[offset].filled {
+                    if row != floor {
+                        self.0[(floor * Board::WIDTH + col) as usize] = self.0[offset];
+                        self.0[offset] = Cell::new();
+                    }
+                    floor -= 1;
+                }
+            }
+        }
+    }
+
+    // Blocks touching side by side or on top of each other fall together,
+    // lowest group first, until none can move.
+    fn settle_groups(&mut self) {
+        loop {
+            let mut moved = false;
+            for group in self.groups() {
+                let mut drop = 0;
+                while group.iter().all(|offset| {
+                    let below = offset + ((drop + 1) * Board::WIDTH) as usize;
+                    below < Board::SIZE as usize
+                        && (!self.0[below].filled || group.contains(&below))
+                }) {
+                    drop += 1;
+                }
+                if drop > 0 {
+                    moved = true;
+                    let cells: Vec<Cell> = group.iter().map(|offset| self.0[*offset]).collect();
+                    for offset in &group {
+                        self.0[*offset] = Cell::new();
+                    }
+                    for (offset, cell) in group.iter().zip(cells) {
+                        self.0[offset + (drop * Board::WIDTH) as usize] = cell;
+                    }
+                    // The groups found before this one fell may have changed.
+                    break;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    // Connected filled cells, the groups resting lowest first.
+    fn groups(&self) -> Vec<Vec<usize>> {
+        let mut seen = [false; Self::SIZE as usize];
+        let mut groups = vec![];
+        for start in (0..Board::SIZE as usize).rev() {
+            if !self.0[start].filled || seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut group = vec![start];
+            let mut next = 0;
+            while next < group.len() {
+                let offset = group[next] as isize;
+                next += 1;
+                let (x, y) = (offset % Board::WIDTH, offset / Board::WIDTH);
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if !(0..Board::WIDTH).contains(&nx) || !(0..Board::HEIGHT).contains(&ny) {
+                        continue;
+                    }
+                    let neighbour = (ny * Board::WIDTH + nx) as usize;
+                    if self.0[neighbour].filled && !seen[neighbour] {
+                        seen[neighbour] = true;
+                        group.push(neighbour);
+                    }
+                }
+            }
+            groups.push(group);
+        }
+        groups
+    }
+
+    // Clears the top marked row only, for collapsing a clear row by row.
+    fn clear_marked_row(&mut self) -> bool {
         self.1.sort();
         self.1.reverse();
-        let mut moved_cell = false;
 
         match self.1.pop() {
             Some(cleared_row) => {
@@ -116,7 +332,6 @@ impl Board {
                         let offset = (row * Board::WIDTH + col) as usize;
                         let offset_below = ((row + 1) * Board::WIDTH + col) as usize;
                         if self.0[offset].filled {
-                            moved_cell = true;
                             self.0[offset_below].filled = true;
                             self.0[offset_below].kind = self.0[offset].kind;
                             self.0[offset] = Cell::new();
@@ -126,49 +341,375 @@ impl Board {
             }
             None => return false,
         }
-        moved_cell
+        true
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
+pub enum Clock {
+    System,
+    Simulated(Instant),
+}
+
+impl Clock {
+    pub fn now(&self) -> Instant {
+        match self {
+            Clock::System => Instant::now(),
+            Clock::Simulated(now) => *now,
+        }
+    }
+
+    pub fn advance(&mut self, step: Duration) {
+        if let Clock::Simulated(now) = self {
+            *now += step;
+        }
+    }
+}
+
+// How much faster than gravity a soft drop falls; sonic drops to the floor
+// at once without locking.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoftDrop {
+    Factor(u32),
+    Sonic,
+}
+
+impl Default for SoftDrop {
+    fn default() -> Self {
+        SoftDrop::Factor(20)
+    }
+}
+
+impl fmt::Display for SoftDrop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoftDrop::Factor(factor) => write!(f, "{}", factor),
+            SoftDrop::Sonic => write!(f, "inf"),
+        }
+    }
+}
+
+impl FromStr for SoftDrop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inf" | "sonic" => Ok(SoftDrop::Sonic),
+            _ => match s.parse() {
+                Ok(factor) if factor >= 1 => Ok(SoftDrop::Factor(factor)),
+                _ => Err(format!(
+                    "soft drop factor must be 1 or more, or inf: {:?}",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+// How long the game pauses between a piece locking and the next one
+// spawning (ARE), shows completed rows before removing them, and lets a
+// landed piece move before it locks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    pub entry_delay: Duration,
+    pub line_clear_delay: Duration,
+    pub lock_delay: Duration,
+}
+
+impl Timing {
+    pub const MODERN: Timing = Timing {
+        entry_delay: Duration::from_millis(100),
+        line_clear_delay: Duration::from_millis(300),
+        lock_delay: Duration::from_millis(500),
+    };
+    // The NES's 10 frames of ARE and about 20 frames of line clear.
+    pub const CLASSIC: Timing = Timing {
+        entry_delay: Duration::from_millis(167),
+        line_clear_delay: Duration::from_millis(333),
+        lock_delay: Duration::from_millis(500),
+    };
+    pub const INSTANT: Timing = Timing {
+        entry_delay: Duration::ZERO,
+        line_clear_delay: Duration::ZERO,
+        lock_delay: Duration::from_millis(500),
+    };
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::MODERN
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}",
+            self.entry_delay.as_millis(),
+            self.line_clear_delay.as_millis(),
+            self.lock_delay.as_millis()
+        )
+    }
+}
+
+// A preset name, or the entry, line clear and lock delays in ms as
+// "ARE/CLEAR/LOCK", the lock delay being optional.
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "modern" => Ok(Timing::MODERN),
+            "classic" => Ok(Timing::CLASSIC),
+            "instant" => Ok(Timing::INSTANT),
+            _ => {
+                let error = || {
+                    format!(
+                        "timing must be modern, classic, instant or ARE/CLEAR/LOCK in ms: {:?}",
+                        s
+                    )
+                };
+                let millis = s
+                    .split('/')
+                    .map(|ms| ms.parse().map(Duration::from_millis))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error())?;
+                match millis[..] {
+                    [entry_delay, line_clear_delay] => Ok(Timing {
+                        entry_delay,
+                        line_clear_delay,
+                        ..Timing::default()
+                    }),
+                    [entry_delay, line_clear_delay, lock_delay] => Ok(Timing {
+                        entry_delay,
+                        line_clear_delay,
+                        lock_delay,
+                    }),
+                    _ => Err(error()),
+                }
+            }
+        }
+    }
+}
+
+// How fast pieces fall: by the level, or straight to the floor the moment
+// they spawn or move over a gap (20G).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Gravity {
+    #[default]
+    Level,
+    TwentyG,
+}
+
+// Whether completed rows all go at once, or the stack collapses one row
+// at a time over the line clear delay.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ClearStyle {
+    #[default]
+    Simultaneous,
+    Sequential,
+}
+
+impl fmt::Display for ClearStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClearStyle::Simultaneous => write!(f, "simultaneous"),
+            ClearStyle::Sequential => write!(f, "sequential"),
+        }
+    }
+}
+
+impl FromStr for ClearStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "simultaneous" => Ok(ClearStyle::Simultaneous),
+            "sequential" => Ok(ClearStyle::Sequential),
+            _ => Err(format!(
+                "clear style must be simultaneous or sequential: {:?}",
+                s
+            )),
+        }
+    }
+}
+
+// How the blocks left above a line clear come down: as whole rows, as
+// connected groups, or block by block into any hole below. Anything but
+// whole rows can complete more rows, which then clear as a chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ClearGravity {
+    #[default]
+    Naive,
+    Sticky,
+    Cascade,
+}
+
+impl fmt::Display for ClearGravity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClearGravity::Naive => write!(f, "naive"),
+            ClearGravity::Sticky => write!(f, "sticky"),
+            ClearGravity::Cascade => write!(f, "cascade"),
+        }
+    }
+}
+
+impl FromStr for ClearGravity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "naive" => Ok(ClearGravity::Naive),
+            "sticky" => Ok(ClearGravity::Sticky),
+            "cascade" => Ok(ClearGravity::Cascade),
+            _ => Err(format!("gravity must be naive, sticky or cascade: {:?}", s)),
+        }
+    }
+}
+
+// When the level goes up, counted from the level a game started on.
+// Classic waits longer before the first level-up from a high start, as
+// the NES does; the guideline goes up every ten lines from the start.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Progression {
+    Classic,
+    #[default]
+    Guideline,
+}
+
+impl Progression {
+    // Lines cleared by which a game begun on start is past level.
+    pub fn lines_to_pass(self, start: usize, level: usize) -> usize {
+        let levels = level.saturating_sub(start);
+        match self {
+            Progression::Classic => {
+                // The NES counts levels from 0.
+                let start = start.saturating_sub(1) * 10;
+                let first = cmp::min(start + 10, cmp::max(100, start.saturating_sub(50)));
+                first + levels * 10
+            }
+            Progression::Guideline => (levels + 1) * 10,
+        }
+    }
+}
+
+impl fmt::Display for Progression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progression::Classic => write!(f, "classic"),
+            Progression::Guideline => write!(f, "guideline"),
+        }
+    }
+}
+
+impl FromStr for Progression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Progression::Classic),
+            "guideline" => Ok(Progression::Guideline),
+            _ => Err(format!("progression must be classic or guideline: {:?}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum EngineState {
     Falling,
     Locking(Instant),
     PatternFinding,
     Animating(Instant),
     EliminatingSpace,
+    Entry(Instant),
 }
 
+#[derive(Clone)]
 pub struct Engine {
     board: Board,
     bag: Vec<Kind>,
-    rng: ThreadRng,
+    rng: ChaCha8Rng,
     last_tick: Instant,
     soft_dropping: bool,
     soft_drop_count: usize,
+    // Whether the piece's last successful move was a rotation.
+    rotated: bool,
+    // Rows in the line clear being animated.
+    clearing: usize,
+    pub clock: Clock,
+    pub soft_drop: SoftDrop,
+    pub timing: Timing,
+    pub clear_style: ClearStyle,
+    pub clear_gravity: ClearGravity,
+    pub gravity: Gravity,
+    pub pieces: usize,
     pub level: usize,
+    // The level the game started on, where progression counts from.
+    pub start_level: usize,
+    pub progression: Progression,
     pub rows_cleared: usize,
     pub points: usize,
     pub state: EngineState,
+    // T-spin of the piece locked last.
+    pub spin: Spin,
+    // Clears set off by the piece locked last, the first one included.
+    pub chain: usize,
     pub queue: VecDeque<Kind>,
     pub cursor: Option<Piece>,
 }
 
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Engine {
     const LEVEL_TPR_IN_MS: [u32; 15] = [
         1000, 793, 618, 473, 355, 262, 190, 135, 94, 64, 43, 28, 18, 11, 7,
     ];
     pub fn new() -> Self {
+        Self::with_rng(ChaCha8Rng::from_entropy())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    // A seeded engine driven by a clock that only moves when advanced,
+    // for running games without a window.
+    pub fn simulated(seed: u64) -> Self {
+        let mut engine = Self::with_seed(seed);
+        engine.clock = Clock::Simulated(engine.last_tick);
+        engine
+    }
+
+    fn with_rng(rng: ChaCha8Rng) -> Self {
         Engine {
             board: Board::blank(),
             bag: Vec::new(),
-            rng: thread_rng(),
-            level: 3,
+            rng,
+            level: 1,
+            start_level: 1,
+            progression: Progression::default(),
             rows_cleared: 0,
             points: 0,
             soft_dropping: false,
             soft_drop_count: 0,
+            rotated: false,
+            clearing: 0,
+            spin: Spin::None,
+            chain: 0,
             last_tick: Instant::now(),
+            clock: Clock::System,
+            soft_drop: SoftDrop::default(),
+            timing: Timing::default(),
+            clear_style: ClearStyle::default(),
+            clear_gravity: ClearGravity::default(),
+            gravity: Gravity::default(),
+            pieces: 0,
             state: EngineState::Falling,
             queue: VecDeque::with_capacity(7),
             cursor: None,
@@ -198,10 +739,44 @@ impl Engine {
         kind
     }
 
+    pub fn set_start_level(&mut self, level: usize) {
+        self.start_level = level.max(1);
+        self.level = self.start_level;
+    }
+
+    // A seed drawn from the game's own randomness, for anything else
+    // random in it to be replayed from the same seed.
+    pub fn random_seed(&mut self) -> u64 {
+        self.rng.gen()
+    }
+
+    // Raises the stack by garbage rows from below; the falling piece goes
+    // up with it where it would otherwise overlap. Errors on top out.
+    pub fn add_garbage(&mut self, holes: &[isize]) -> Result<(), String> {
+        if self.board.add_garbage(holes) {
+            return Err("Topped out by garbage".to_string());
+        }
+        if let Some(c) = self.cursor.as_mut() {
+            let overlaps = |c: &Piece| c.cells().iter().any(|cell| self.board.filled(*cell));
+            for _ in 0..holes.len() {
+                if !overlaps(c) {
+                    break;
+                }
+                c.position.y -= 1;
+                c.current_position.y -= 1;
+            }
+            if overlaps(c) {
+                return Err("Topped out by garbage".to_string());
+            }
+        }
+        Ok(())
+    }
+
     pub fn clear_board(&mut self) {
         self.points = 0;
         self.rows_cleared = 0;
-        self.level = 1;
+        self.pieces = 0;
+        self.level = self.start_level;
         self.soft_dropping = false;
         self.soft_drop_count = 0;
         self.board = Board::blank();
@@ -215,14 +790,12 @@ impl Engine {
     }
 
     pub fn place_cursor(&mut self) {
-        self.cursor = Some(Piece {
-            kind: self.pull_from_queue(),
-            current_position: Coordinate::new((Board::WIDTH as isize / 2) - 2, -2),
-            offset: 0.0,
-            // NB: We start OFF SCREEN!
-            position: Vector2::new((Board::WIDTH as isize / 2) - 2, -2),
-            rotation: Rotation::N,
-        });
+        self.rotated = false;
+        self.cursor = Some(Piece::spawn(self.pull_from_queue()));
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
     }
 
     pub fn try_move(&mut self, direction: Direction) {
@@ -231,26 +804,46 @@ impl Engine {
             Direction::RIGHT => self.left_or_right(direction),
             Direction::CW => self.cw(),
             Direction::CCW => self.ccw(),
+            Direction::FLIP => self.flip(),
         }
     }
 
     fn left_or_right(&mut self, direction: Direction) {
         if let Some(c) = self.cursor.as_mut() {
             if c.can_move_lateral(&self.board, direction) {
-                c.lateral_move(direction)
+                c.lateral_move(direction);
+                self.rotated = false;
             }
         }
     }
 
     fn cw(&mut self) {
-        if let Some(c) = self.cursor.as_mut() {
-            c.cw(&self.board);
-        }
+        self.rotate(|c, board| c.cw(board));
     }
 
     fn ccw(&mut self) {
+        self.rotate(|c, board| c.ccw(board));
+    }
+
+    fn flip(&mut self) {
+        self.rotate(|c, board| c.flip(board));
+    }
+
+    fn rotate(&mut self, turn: impl Fn(&mut Piece, &Board)) {
         if let Some(c) = self.cursor.as_mut() {
-            c.ccw(&self.board);
+            let rotation = c.rotation;
+            turn(c, &self.board);
+            if c.rotation != rotation {
+                self.rotated = true;
+            }
+        }
+    }
+
+    fn lock_spin(&self, piece: &Piece) -> Spin {
+        if self.rotated {
+            piece.t_spin(&self.board)
+        } else {
+            Spin::None
         }
     }
 
@@ -263,7 +856,7 @@ impl Engine {
                         x: offset % Board::WIDTH,
                         y: offset / Board::WIDTH,
                     },
-                    kind: self.board.0[offset as usize].kind.unwrap_or(Kind::O),
+                    kind: self.board.0[offset as usize].kind,
                 })
             }
         }
@@ -295,30 +888,41 @@ impl Engine {
         self.soft_dropping = soft_drop;
         match self.state {
             EngineState::Falling => match &mut self.cursor {
-                None => {
-                    self.place_cursor();
-                    if let Some(c) = &self.cursor {
-                        if !c.can_lower(&self.board) {
-                            return Result::Err("Cannot lower new cursor".to_string());
+                None => return self.spawn(),
+                Some(c) => {
+                    let now = self.clock.now();
+                    if self.gravity == Gravity::TwentyG {
+                        while c.can_lower(&self.board) {
+                            *c = c.lower();
+                            self.rotated = false;
                         }
                     }
-                }
-                Some(c) => {
-                    let now = Instant::now();
+                    if soft_drop && self.soft_drop == SoftDrop::Sonic {
+                        while c.can_lower(&self.board) {
+                            *c = c.lower();
+                            self.soft_drop_count += 1;
+                            self.rotated = false;
+                        }
+                    }
+                    let factor = match self.soft_drop {
+                        SoftDrop::Factor(factor) => factor.max(1),
+                        SoftDrop::Sonic => u32::MAX,
+                    };
                     let duration_divisor = if soft_drop || stopped_soft_dropping {
-                        20
+                        factor
                     } else {
                         1
                     };
-                    let level_tick_duration =
-                        Self::LEVEL_TPR_IN_MS[self.level - 1] / duration_divisor;
+                    let gravity_duration = Self::LEVEL_TPR_IN_MS
+                        [cmp::min(self.level, Self::LEVEL_TPR_IN_MS.len()) - 1];
+                    let level_tick_duration = cmp::max(1, gravity_duration / duration_divisor);
                     let elapsed = now - self.last_tick;
                     let percentage = cmp::min(level_tick_duration, elapsed.as_millis() as u32)
                         as f32
                         / level_tick_duration as f32;
                     c.offset = percentage;
                     if stopped_soft_dropping {
-                        let new_level_tick_duration = level_tick_duration * 20;
+                        let new_level_tick_duration = gravity_duration;
                         /* old duration, old percentage
                         new duration, SAME percentage --> self.tick needs to
                         move backwards in time.
@@ -336,10 +940,11 @@ impl Engine {
                                 self.soft_drop_count += 1;
                             }
                             self.cursor = Some(c.lower());
+                            self.rotated = false;
                             self.last_tick = now;
                             return Result::Ok(());
                         } else {
-                            self.state = EngineState::Locking(Instant::now());
+                            self.state = EngineState::Locking(self.clock.now());
                             return Result::Ok(());
                         }
                     }
@@ -349,61 +954,116 @@ impl Engine {
                 if let Some(c) = &self.cursor {
                     if c.can_lower(&self.board) {
                         self.cursor = Some(c.lower());
+                        self.rotated = false;
                         self.state = EngineState::Falling;
                         return Result::Ok(());
                     }
                 }
-                if (Instant::now() - start) > Duration::from_millis(500) {
+                if (self.clock.now() - start) >= self.timing.lock_delay {
                     if let Some(c) = &self.cursor {
                         if self.soft_dropping {
                             self.points += self.level * self.soft_drop_count;
                             self.soft_drop_count = 0;
                         }
+                        self.spin = self.lock_spin(c);
+                        self.chain = 0;
                         self.board.add(c)?;
+                        self.pieces += 1;
                         self.cursor = None;
                     }
                     self.state = EngineState::PatternFinding;
                 }
             }
             EngineState::PatternFinding => {
-                match self.board.has_patterns(&mut self.points, &mut self.level) {
-                    true => self.state = EngineState::Animating(Instant::now()),
-                    false => self.state = EngineState::Falling,
+                match self.board.has_patterns(
+                    &mut self.points,
+                    &mut self.level,
+                    self.spin,
+                    self.chain + 1,
+                ) {
+                    true => {
+                        self.chain += 1;
+                        self.clearing = self.board.1.len();
+                        self.state = EngineState::Animating(self.clock.now());
+                    }
+                    false => self.state = EngineState::Entry(self.clock.now()),
                 }
             }
-            EngineState::Animating(start) => {
-                if (Instant::now() - start) > Duration::from_millis(100) {
-                    if self.board.clear_marked() {
-                        self.rows_cleared += 1;
-                        if self.rows_cleared >= (self.level * 10) {
-                            self.level += 1;
+            // Only whole rows can come down one cleared row at a time.
+            EngineState::Animating(start) => match self.clear_style {
+                ClearStyle::Sequential if self.clear_gravity == ClearGravity::Naive => {
+                    let step = self.timing.line_clear_delay / self.clearing.max(1) as u32;
+                    if (self.clock.now() - start) >= step {
+                        if self.board.clear_marked_row() {
+                            self.count_rows(1);
                         }
-                        self.state = EngineState::EliminatingSpace;
-                    } else {
-                        self.state = EngineState::Falling;
+                        self.state = match self.board.1.is_empty() {
+                            true => EngineState::Entry(self.clock.now()),
+                            false => EngineState::EliminatingSpace,
+                        };
                     }
                 }
-            }
+                _ => {
+                    if (self.clock.now() - start) >= self.timing.line_clear_delay {
+                        let cleared = self.board.clear_marked_with(self.clear_gravity);
+                        self.count_rows(cleared);
+                        // What fell may have completed rows of its own.
+                        self.state = match self.clear_gravity {
+                            ClearGravity::Naive => EngineState::Entry(self.clock.now()),
+                            _ => {
+                                self.spin = Spin::None;
+                                EngineState::PatternFinding
+                            }
+                        };
+                    }
+                }
+            },
             EngineState::EliminatingSpace => {
                 // Reset animation timer, "eliminating space" is in
                 // the drawing code more concretely speaking.
-                self.state = EngineState::Animating(Instant::now());
+                self.state = EngineState::Animating(self.clock.now());
+            }
+            EngineState::Entry(start) => {
+                if (self.clock.now() - start) >= self.timing.entry_delay {
+                    self.state = EngineState::Falling;
+                    return self.spawn();
+                }
             }
         }
 
         Result::Ok(())
     }
 
+    fn count_rows(&mut self, rows: usize) {
+        for _ in 0..rows {
+            self.rows_cleared += 1;
+            if self.rows_cleared >= self.progression.lines_to_pass(self.start_level, self.level) {
+                self.level += 1;
+            }
+        }
+    }
+
+    fn spawn(&mut self) -> Result<(), String> {
+        self.place_cursor();
+        self.last_tick = self.clock.now();
+        if let Some(c) = &self.cursor {
+            if !c.can_lower(&self.board) {
+                return Result::Err("Cannot lower new cursor".to_string());
+            }
+        }
+        Ok(())
+    }
+
     pub fn drop(&mut self) -> Result<(), String> {
         match &self.cursor {
             None => (),
             Some(c) => {
                 let mut p = Piece {
-                    kind: c.kind.clone(),
-                    current_position: c.position.clone(),
+                    kind: c.kind,
+                    current_position: c.position,
                     offset: c.offset,
-                    position: c.position.clone(),
-                    rotation: c.rotation.clone(),
+                    position: c.position,
+                    rotation: c.rotation,
                 };
                 let mut drop_height = 0;
                 while p.can_lower(&self.board) {
@@ -411,9 +1071,16 @@ impl Engine {
                     p = p.lower();
                 }
                 self.points += 2 * drop_height;
-                if let Err(_) = self.board.add(&p) {
+                self.spin = if drop_height > 0 {
+                    Spin::None
+                } else {
+                    self.lock_spin(&p)
+                };
+                self.chain = 0;
+                if self.board.add(&p).is_err() {
                     return Err("Game Over".to_string());
                 }
+                self.pieces += 1;
                 self.cursor = None;
                 self.state = EngineState::PatternFinding;
             }
@@ -421,3 +1088,188 @@ impl Engine {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bottom rows of the board, as Board::from_rows takes them.
+    fn bottom(board: &Board, rows: isize) -> Vec<String> {
+        (Board::HEIGHT - rows..Board::HEIGHT)
+            .map(|y| {
+                (0..Board::WIDTH)
+                    .map(|x| match board.filled(Coordinate::new(x, y)) {
+                        true => 'X',
+                        false => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // A full row with a hook and a lone block above it, and a floor with
+    // holes under the hook.
+    const HOOK: [&str; 4] = ["XX........", ".X......X.", "XXXXXXXXXX", "..XXXXXXXX"];
+
+    // Marks and clears the full rows by the gravity, returning how many.
+    fn clear(board: &mut Board, gravity: ClearGravity) -> usize {
+        let (mut points, mut level) = (0, 1);
+        board.has_patterns(&mut points, &mut level, Spin::None, 1);
+        board.clear_marked_with(gravity)
+    }
+
+    #[test]
+    fn timing_presets_and_delays() {
+        assert_eq!("modern".parse(), Ok(Timing::MODERN));
+        assert_eq!("classic".parse(), Ok(Timing::CLASSIC));
+        assert_eq!("instant".parse(), Ok(Timing::INSTANT));
+        assert_eq!(
+            "50/200".parse(),
+            Ok(Timing {
+                entry_delay: Duration::from_millis(50),
+                line_clear_delay: Duration::from_millis(200),
+                lock_delay: Timing::default().lock_delay,
+            })
+        );
+        let timing = Timing {
+            entry_delay: Duration::ZERO,
+            line_clear_delay: Duration::from_millis(100),
+            lock_delay: Duration::from_millis(250),
+        };
+        assert_eq!("0/100/250".parse(), Ok(timing));
+        assert_eq!(timing.to_string().parse(), Ok(timing));
+        for bad in ["", "fast", "100", "1/2/3/4", "a/b", "100/-1"] {
+            assert!(bad.parse::<Timing>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn collapse_removes_full_rows() {
+        let mut board = Board::from_rows(&["..X.......", "XXXXXXXXXX", "X.........", "XXXXXXXXXX"]);
+        assert_eq!(board.collapse(|cell| cell.filled), 2);
+        assert_eq!(
+            bottom(&board, 3),
+            ["..........", "..X.......", "X........."]
+        );
+    }
+
+    #[test]
+    fn naive_gravity_moves_whole_rows() {
+        let mut board = Board::from_rows(&HOOK);
+        assert_eq!(clear(&mut board, ClearGravity::Naive), 1);
+        assert_eq!(
+            bottom(&board, 3),
+            ["XX........", ".X......X.", "..XXXXXXXX"]
+        );
+    }
+
+    #[test]
+    fn sticky_gravity_drops_connected_groups() {
+        let mut board = Board::from_rows(&HOOK);
+        assert_eq!(clear(&mut board, ClearGravity::Sticky), 1);
+        assert_eq!(
+            bottom(&board, 3),
+            ["..........", "XX......X.", ".XXXXXXXXX"]
+        );
+    }
+
+    #[test]
+    fn cascade_gravity_drops_blocks_and_chains() {
+        let mut board = Board::from_rows(&HOOK);
+        assert_eq!(clear(&mut board, ClearGravity::Cascade), 1);
+        assert_eq!(
+            bottom(&board, 3),
+            ["..........", ".X......X.", "XXXXXXXXXX"]
+        );
+        assert_eq!(clear(&mut board, ClearGravity::Cascade), 1);
+        assert_eq!(bottom(&board, 1), [".X......X."]);
+    }
+
+    #[test]
+    fn groups_are_connected_cells_lowest_first() {
+        let board = Board::from_rows(&["XX.......X", ".X.......X", "......XX.."]);
+        let mut groups = board.groups();
+        assert_eq!(
+            groups.iter().map(|group| group.len()).collect::<Vec<_>>(),
+            [2, 2, 3]
+        );
+        groups[0].sort();
+        assert_eq!(groups[0], [196, 197]);
+    }
+
+    #[test]
+    fn classic_progression_waits_from_high_starts() {
+        let classic = Progression::Classic;
+        // NES levels 0, 9, 15, 18 and 19.
+        assert_eq!(classic.lines_to_pass(1, 1), 10);
+        assert_eq!(classic.lines_to_pass(1, 2), 20);
+        assert_eq!(classic.lines_to_pass(10, 10), 100);
+        assert_eq!(classic.lines_to_pass(16, 16), 100);
+        assert_eq!(classic.lines_to_pass(19, 19), 130);
+        assert_eq!(classic.lines_to_pass(19, 20), 140);
+        assert_eq!(classic.lines_to_pass(20, 20), 140);
+    }
+
+    #[test]
+    fn guideline_progression_goes_up_every_ten_lines() {
+        let guideline = Progression::Guideline;
+        assert_eq!(guideline.lines_to_pass(1, 1), 10);
+        assert_eq!(guideline.lines_to_pass(5, 5), 10);
+        assert_eq!(guideline.lines_to_pass(5, 7), 30);
+    }
+
+    #[test]
+    fn levels_count_from_the_start_level() {
+        let mut engine = Engine::simulated(0);
+        engine.progression = Progression::Classic;
+        engine.set_start_level(19);
+        engine.count_rows(129);
+        assert_eq!(engine.level, 19);
+        engine.count_rows(1);
+        assert_eq!(engine.level, 20);
+        engine.count_rows(10);
+        assert_eq!(engine.level, 21);
+    }
+
+    #[test]
+    fn garbage_goes_in_under_the_stack() {
+        let mut board = Board::blank();
+        let mut piece = Piece::spawn(Kind::O);
+        piece.position.y = Board::HEIGHT - 2;
+        board.add(&piece).unwrap();
+        assert!(!board.add_garbage(&[0, 9]));
+        assert_eq!(
+            bottom(&board, 4),
+            ["....XX....", "....XX....", ".XXXXXXXXX", "XXXXXXXXX."]
+        );
+        assert_eq!(board.garbage_rows(), 2);
+        // Completing the bottom row clears one of them.
+        board.0[(Board::SIZE - 1) as usize].filled = true;
+        assert_eq!(board.collapse(|cell| cell.filled), 1);
+        assert_eq!(board.garbage_rows(), 1);
+    }
+
+    #[test]
+    fn garbage_pushing_blocks_out_of_the_top_overflows() {
+        let mut rows = vec!["X........."];
+        rows.extend([".........."; 19]);
+        let mut board = Board::from_rows(&rows);
+        assert!(board.add_garbage(&[0]));
+    }
+
+    #[test]
+    fn garbage_raises_the_falling_piece_out_of_the_stack() {
+        let mut engine = Engine::simulated(0);
+        let mut piece = Piece::spawn(Kind::O);
+        piece.position.y = Board::HEIGHT - 2;
+        piece.current_position = piece.position;
+        engine.cursor = Some(piece);
+        engine.add_garbage(&[0]).unwrap();
+        let raised = engine.cursor.unwrap();
+        assert_eq!(raised.position.y, Board::HEIGHT - 3);
+        assert!(raised
+            .cells()
+            .iter()
+            .all(|cell| !engine.board().filled(*cell)));
+    }
+}
@@ -0,0 +1,226 @@
+use super::Board;
+
+const COLUMNS: usize = Board::WIDTH as usize;
+
+// Height of each column's highest filled cell above the floor, and what
+// follows from the heights alone, for an evaluation to find them once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Heights(pub [isize; COLUMNS]);
+
+impl Heights {
+    pub fn aggregate(&self) -> isize {
+        self.0.iter().sum()
+    }
+
+    pub fn bumpiness(&self) -> isize {
+        self.0
+            .windows(2)
+            .map(|pair| (pair[0] - pair[1]).abs())
+            .sum()
+    }
+
+    // How far each column sits below the lower of its neighbours, the walls
+    // being as high as the board.
+    pub fn well_depths(&self) -> [isize; COLUMNS] {
+        let heights = self.0;
+        let mut depths = [0; COLUMNS];
+        for (x, depth) in depths.iter_mut().enumerate() {
+            let left = if x == 0 {
+                Board::HEIGHT
+            } else {
+                heights[x - 1]
+            };
+            let right = heights.get(x + 1).copied().unwrap_or(Board::HEIGHT);
+            *depth = (left.min(right) - heights[x]).max(0);
+        }
+        depths
+    }
+}
+
+impl Board {
+    fn is_filled(&self, x: isize, y: isize) -> bool {
+        self.0[(y * Board::WIDTH + x) as usize].filled
+    }
+
+    pub fn column_heights(&self) -> Heights {
+        let mut heights = [0; COLUMNS];
+        for (x, height) in heights.iter_mut().enumerate() {
+            *height = (0..Board::HEIGHT)
+                .find(|y| self.is_filled(x as isize, *y))
+                .map_or(0, |y| Board::HEIGHT - y);
+        }
+        Heights(heights)
+    }
+
+    pub fn aggregate_height(&self) -> isize {
+        self.column_heights().aggregate()
+    }
+
+    // Empty cells with a filled cell somewhere above them.
+    pub fn holes(&self) -> usize {
+        (0..Board::WIDTH)
+            .map(|x| {
+                let mut covered = false;
+                let mut holes = 0;
+                for y in 0..Board::HEIGHT {
+                    if self.is_filled(x, y) {
+                        covered = true;
+                    } else if covered {
+                        holes += 1;
+                    }
+                }
+                holes
+            })
+            .sum()
+    }
+
+    // Filled cells stacked on top of a hole in their column.
+    pub fn covered_cells(&self) -> usize {
+        (0..Board::WIDTH)
+            .map(|x| {
+                // Filled cells seen so far, and those above the lowest hole.
+                let mut filled = 0;
+                let mut covered = 0;
+                for y in 0..Board::HEIGHT {
+                    if self.is_filled(x, y) {
+                        filled += 1;
+                    } else if filled > 0 {
+                        covered = filled;
+                    }
+                }
+                covered
+            })
+            .sum()
+    }
+
+    pub fn bumpiness(&self) -> isize {
+        self.column_heights().bumpiness()
+    }
+
+    // Changes between filled and empty along each row, the walls counting
+    // as filled.
+    pub fn row_transitions(&self) -> usize {
+        let mut transitions = 0;
+        for y in 0..Board::HEIGHT {
+            let mut previous = true;
+            for x in 0..Board::WIDTH {
+                let filled = self.is_filled(x, y);
+                if filled != previous {
+                    transitions += 1;
+                }
+                previous = filled;
+            }
+            if !previous {
+                transitions += 1;
+            }
+        }
+        transitions
+    }
+
+    // Changes between filled and empty down each column, the floor counting
+    // as filled.
+    pub fn column_transitions(&self) -> usize {
+        let mut transitions = 0;
+        for x in 0..Board::WIDTH {
+            let mut previous = false;
+            for y in 0..Board::HEIGHT {
+                let filled = self.is_filled(x, y);
+                if filled != previous {
+                    transitions += 1;
+                }
+                previous = filled;
+            }
+            if !previous {
+                transitions += 1;
+            }
+        }
+        transitions
+    }
+
+    pub fn well_depths(&self) -> [isize; COLUMNS] {
+        self.column_heights().well_depths()
+    }
+
+    // Column and depth of the deepest well, the leftmost one on a tie.
+    pub fn deepest_well(&self) -> Option<(usize, isize)> {
+        self.well_depths()
+            .iter()
+            .enumerate()
+            .filter(|(_, depth)| **depth > 0)
+            .fold(None, |deepest: Option<(usize, isize)>, (x, depth)| {
+                match deepest {
+                    Some((_, best)) if best >= *depth => deepest,
+                    _ => Some((x, *depth)),
+                }
+            })
+    }
+
+    // Whether a vertical I dropped into some column would clear four rows.
+    pub fn tetris_ready(&self) -> bool {
+        let heights = self.column_heights().0;
+        (0..Board::WIDTH).any(|well| {
+            let bottom = Board::HEIGHT - 1 - heights[well as usize];
+            bottom >= 3
+                && (bottom - 3..=bottom).all(|y| {
+                    (0..Board::WIDTH).all(|x| (x == well) != self.is_filled(x, y))
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_board() {
+        let board = Board::from_rows(&[]);
+        assert_eq!(board.column_heights(), Heights([0; COLUMNS]));
+        assert_eq!(board.aggregate_height(), 0);
+        assert_eq!(board.holes(), 0);
+        assert_eq!(board.covered_cells(), 0);
+        assert_eq!(board.bumpiness(), 0);
+        // Both walls of every row, and the floor under every column.
+        assert_eq!(board.row_transitions(), 40);
+        assert_eq!(board.column_transitions(), 10);
+        assert_eq!(board.well_depths(), [0; COLUMNS]);
+        assert_eq!(board.deepest_well(), None);
+        assert!(!board.tetris_ready());
+    }
+
+    #[test]
+    fn covered_hole() {
+        let board = Board::from_rows(&["X.........", "XX........", ".X........"]);
+        assert_eq!(
+            board.column_heights(),
+            Heights([3, 2, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(board.aggregate_height(), 5);
+        assert_eq!(board.holes(), 1);
+        assert_eq!(board.covered_cells(), 2);
+        assert_eq!(board.bumpiness(), 3);
+        assert_eq!(board.row_transitions(), 42);
+        assert_eq!(board.column_transitions(), 12);
+        assert_eq!(board.well_depths(), [0; COLUMNS]);
+        assert_eq!(board.deepest_well(), None);
+        assert!(!board.tetris_ready());
+    }
+
+    #[test]
+    fn right_well_four_deep() {
+        let board = Board::from_rows(&["XXXXXXXXX."; 4]);
+        assert_eq!(
+            board.column_heights(),
+            Heights([4, 4, 4, 4, 4, 4, 4, 4, 4, 0])
+        );
+        assert_eq!(board.aggregate_height(), 36);
+        assert_eq!(board.holes(), 0);
+        assert_eq!(board.covered_cells(), 0);
+        assert_eq!(board.bumpiness(), 4);
+        assert_eq!(board.row_transitions(), 40);
+        assert_eq!(board.column_transitions(), 10);
+        assert_eq!(board.well_depths(), [0, 0, 0, 0, 0, 0, 0, 0, 0, 4]);
+        assert_eq!(board.deepest_well(), Some((9, 4)));
+        assert!(board.tetris_ready());
+    }
+}
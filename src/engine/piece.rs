@@ -1,9 +1,18 @@
 use std::collections::HashMap;
 
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 
 use super::{Board, Coordinate};
 
+lazy_static! {
+    static ref CELLS: HashMap<(Kind, Rotation), Vec<Coordinate>> = Kind::ALL
+        .iter()
+        .flat_map(|kind| Rotation::ALL.iter().map(move |rotation| (*kind, *rotation)))
+        .map(|(kind, rotation)| ((kind, rotation), kind.parse_cells(&rotation)))
+        .collect();
+}
+
 lazy_static! {
     static ref SHAPES: HashMap<&'static str, HashMap<&'static str, &'static str>> =
         HashMap::from([
@@ -73,7 +82,7 @@ lazy_static! {
         ]);
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Rotation {
     N,
     E,
@@ -81,12 +90,17 @@ pub enum Rotation {
     W,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+impl Rotation {
+    pub const ALL: [Self; 4] = [Self::N, Self::E, Self::S, Self::W];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     LEFT,
     RIGHT,
     CW,
     CCW,
+    FLIP,
 }
 
 impl Direction {
@@ -96,11 +110,20 @@ impl Direction {
             Direction::RIGHT => 1,
             Direction::CW => panic!("Shouldn't be taking value on a CW"),
             Direction::CCW => panic!("Shouldn't be taking value on a CCW"),
+            Direction::FLIP => panic!("Shouldn't be taking value on a FLIP"),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+// What a locked T piece counts as, by the three corner rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Kind {
     O,
     I,
@@ -134,19 +157,22 @@ impl Kind {
 
         for (y, line) in lines.into_iter().enumerate() {
             for (x, c) in line.chars().enumerate() {
-                match c {
-                    'X' => res.push(Coordinate {
+                if c == 'X' {
+                    res.push(Coordinate {
                         x: x as isize,
                         y: y as isize,
-                    }),
-                    _ => (),
+                    });
                 }
             }
         }
         res
     }
 
-    fn cells_for(&self, rotation: &Rotation) -> Vec<Coordinate> {
+    fn cells_for(&self, rotation: &Rotation) -> &'static [Coordinate] {
+        &CELLS[&(*self, *rotation)]
+    }
+
+    fn parse_cells(&self, rotation: &Rotation) -> Vec<Coordinate> {
         match self {
             Kind::O => match rotation {
                 Rotation::N => self.string_to_cells(SHAPES["O"]["N"]),
@@ -194,7 +220,7 @@ impl Kind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Piece {
     pub current_position: Coordinate,
     pub offset: f32,
@@ -204,6 +230,18 @@ pub struct Piece {
 }
 
 impl Piece {
+    pub fn spawn(kind: Kind) -> Self {
+        // NB: We start OFF SCREEN!
+        let position = Coordinate::new((Board::WIDTH / 2) - 2, -2);
+        Piece {
+            kind,
+            current_position: position,
+            offset: 0.0,
+            position,
+            rotation: Rotation::N,
+        }
+    }
+
     pub fn get_cells(&self) -> Vec<Coordinate> {
         let shape = self.kind.cells_for(&self.rotation);
         shape
@@ -217,6 +255,16 @@ impl Piece {
             .collect()
     }
 
+    // Cells at the logical position, where get_cells() is the drawing
+    // position that trails it by a row while the piece is falling.
+    pub fn cells(&self) -> Vec<Coordinate> {
+        let shape = self.kind.cells_for(&self.rotation);
+        shape
+            .iter()
+            .map(|mino| Coordinate::new(mino.x + self.position.x, mino.y + self.position.y))
+            .collect()
+    }
+
     pub fn can_move_lateral(&mut self, board: &Board, direction: Direction) -> bool {
         self.check_new_position(board, |cell, offset| Coordinate {
             x: cell.x + direction.value(),
@@ -236,38 +284,137 @@ impl Piece {
     }
 
     pub fn cw(&mut self, board: &Board) {
-        let current_rotation = self.rotation;
-        match self.rotation {
-            Rotation::N => self.rotation = Rotation::E,
-            Rotation::E => self.rotation = Rotation::S,
-            Rotation::S => self.rotation = Rotation::W,
-            Rotation::W => self.rotation = Rotation::N,
-        }
-        if !self.check_new_position(board, |cell, _| cell) {
-            self.rotation = current_rotation;
-        }
+        let (rotation, from) = match self.rotation {
+            Rotation::N => (Rotation::E, 0),
+            Rotation::E => (Rotation::S, 1),
+            Rotation::S => (Rotation::W, 2),
+            Rotation::W => (Rotation::N, 3),
+        };
+        self.turn(board, rotation, &self.quarter_kicks()[from]);
     }
 
     pub fn ccw(&mut self, board: &Board) {
+        // The same kicks as the clockwise turn back, the other way round.
+        let (rotation, back) = match self.rotation {
+            Rotation::N => (Rotation::W, 3),
+            Rotation::E => (Rotation::N, 0),
+            Rotation::S => (Rotation::E, 1),
+            Rotation::W => (Rotation::S, 2),
+        };
+        let kicks = self.quarter_kicks()[back].map(|(dx, dy)| (-dx, -dy));
+        self.turn(board, rotation, &kicks);
+    }
+
+    // Offsets tried in turn for a clockwise quarter turn from each
+    // rotation, y pointing down: the SRS tables, one for the I and one for
+    // the rest. The O turns in place.
+    const QUARTER_KICKS: [[(isize, isize); 5]; 4] = [
+        [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    ];
+    const I_QUARTER_KICKS: [[(isize, isize); 5]; 4] = [
+        [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+        [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+        [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+        [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+    ];
+    const O_QUARTER_KICKS: [[(isize, isize); 5]; 4] = [[(0, 0); 5]; 4];
+
+    fn quarter_kicks(&self) -> [[(isize, isize); 5]; 4] {
+        match self.kind {
+            Kind::O => Piece::O_QUARTER_KICKS,
+            Kind::I => Piece::I_QUARTER_KICKS,
+            _ => Piece::QUARTER_KICKS,
+        }
+    }
+
+    // Offsets tried in turn for a 180 degree turn from each rotation, y
+    // pointing down.
+    const FLIP_KICKS: [[(isize, isize); 6]; 4] = [
+        [(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)],
+        [(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)],
+        [(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)],
+        [(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)],
+    ];
+
+    pub fn flip(&mut self, board: &Board) {
+        let (rotation, kicks) = match self.rotation {
+            Rotation::N => (Rotation::S, Piece::FLIP_KICKS[0]),
+            Rotation::E => (Rotation::W, Piece::FLIP_KICKS[1]),
+            Rotation::S => (Rotation::N, Piece::FLIP_KICKS[2]),
+            Rotation::W => (Rotation::E, Piece::FLIP_KICKS[3]),
+        };
+        self.turn(board, rotation, &kicks);
+    }
+
+    // Turns to the rotation at the first kick that fits, if any does.
+    fn turn(&mut self, board: &Board, rotation: Rotation, kicks: &[(isize, isize)]) {
         let current_rotation = self.rotation;
-        match self.rotation {
-            Rotation::N => self.rotation = Rotation::W,
-            Rotation::W => self.rotation = Rotation::S,
-            Rotation::S => self.rotation = Rotation::E,
-            Rotation::E => self.rotation = Rotation::N,
+        self.rotation = rotation;
+        for &(dx, dy) in kicks {
+            if self.fits(board, dx, dy) {
+                let kick = Coordinate::new(dx, dy);
+                self.position += kick;
+                self.current_position += kick;
+                return;
+            }
+        }
+        self.rotation = current_rotation;
+    }
+
+    // Only meaningful for a T whose last move was a rotation: a spin when
+    // three of the corners around its centre are blocked, and a mini one
+    // unless both corners on the pointing side are among them.
+    pub fn t_spin(&self, board: &Board) -> Spin {
+        if self.kind != Kind::T {
+            return Spin::None;
         }
-        if !self.check_new_position(board, |cell, _| cell) {
-            self.rotation = current_rotation;
+        let centre = self.position + Coordinate::new(1, 1);
+        let blocked = |dx: isize, dy: isize| {
+            let coord = centre + Coordinate::new(dx, dy);
+            coord.x < 0 || coord.x >= Board::WIDTH || board.filled(coord)
+        };
+        let front = match self.rotation {
+            Rotation::N => [(-1, -1), (1, -1)],
+            Rotation::E => [(1, -1), (1, 1)],
+            Rotation::S => [(-1, 1), (1, 1)],
+            Rotation::W => [(-1, -1), (-1, 1)],
+        };
+        let corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+            .iter()
+            .filter(|(dx, dy)| blocked(*dx, *dy))
+            .count();
+        if corners < 3 {
+            Spin::None
+        } else if front.iter().all(|(dx, dy)| blocked(*dx, *dy)) {
+            Spin::Full
+        } else {
+            Spin::Mini
         }
     }
 
+    // Whether the piece fits moved by (dx, dy) from its logical position.
+    // Turns are tested there rather than at the drawn position, which
+    // trails a row behind while falling: a piece that has already reached
+    // a block could otherwise turn into it, and the placement generator,
+    // which only knows logical positions, would disagree with the game.
+    fn fits(&self, board: &Board, dx: isize, dy: isize) -> bool {
+        self.check_new_position(board, |cell, offset| Coordinate {
+            x: cell.x + dx,
+            y: cell.y + offset + dy,
+        })
+    }
+
     fn check_new_position(
         &self,
         board: &Board,
         position_adjuster: impl Fn(Coordinate, isize) -> Coordinate,
     ) -> bool {
         let offset_adjustment = self.position.y - self.current_position.y;
-        for cell in self.get_cells() {
+        for mino in self.kind.cells_for(&self.rotation) {
+            let cell = mino + self.current_position;
             let next_coord = position_adjuster(cell, offset_adjustment);
 
             if next_coord.x < 0 || next_coord.x >= Board::WIDTH || next_coord.y >= Board::HEIGHT {
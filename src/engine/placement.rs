@@ -0,0 +1,179 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::piece::{Direction, Kind, Piece, Rotation};
+use super::Board;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Input {
+    Move(Direction),
+    // One row of soft drop.
+    SoftDrop,
+    HardDrop,
+}
+
+#[derive(Clone, Debug)]
+pub struct Placement {
+    // The piece where it comes to rest.
+    pub piece: Piece,
+    // Shortest sequence reaching it from the starting piece, always ending
+    // with a hard drop.
+    pub inputs: Vec<Input>,
+}
+
+impl Placement {
+    pub fn key(&self) -> Vec<(isize, isize)> {
+        landing_key(&self.piece)
+    }
+}
+
+const MOVES: [Input; 6] = [
+    Input::Move(Direction::LEFT),
+    Input::Move(Direction::RIGHT),
+    Input::Move(Direction::CW),
+    Input::Move(Direction::CCW),
+    Input::Move(Direction::FLIP),
+    Input::SoftDrop,
+];
+
+fn landing_key(piece: &Piece) -> Vec<(isize, isize)> {
+    let mut cells: Vec<(isize, isize)> = piece.cells().iter().map(|c| (c.x, c.y)).collect();
+    cells.sort();
+    cells
+}
+
+fn settle(piece: &Piece) -> Piece {
+    Piece {
+        current_position: piece.position,
+        offset: 0.0,
+        ..*piece
+    }
+}
+
+fn apply(board: &Board, piece: &Piece, input: Input) -> Option<Piece> {
+    let mut next = *piece;
+    match input {
+        Input::Move(direction @ (Direction::LEFT | Direction::RIGHT)) => {
+            if !next.can_move_lateral(board, direction) {
+                return None;
+            }
+            next.lateral_move(direction);
+        }
+        Input::Move(Direction::CW) => next.cw(board),
+        Input::Move(Direction::CCW) => next.ccw(board),
+        Input::Move(Direction::FLIP) => next.flip(board),
+        Input::SoftDrop => {
+            if !next.can_lower(board) {
+                return None;
+            }
+            next = settle(&next.lower());
+        }
+        Input::HardDrop => {
+            while next.can_lower(board) {
+                next = settle(&next.lower());
+            }
+        }
+    }
+    if next.position == piece.position && next.rotation == piece.rotation {
+        return None;
+    }
+    Some(next)
+}
+
+// Every distinct resting place the piece can reach with the game's own
+// movement rules, in order of how few inputs it takes to get there.
+pub fn reachable_placements(board: &Board, piece: &Piece) -> Vec<Placement> {
+    let start = settle(piece);
+    let mut placements: Vec<Placement> = vec![];
+    let mut landings: HashSet<Vec<(isize, isize)>> = HashSet::new();
+    let mut seen: HashSet<(isize, isize, Rotation)> = HashSet::new();
+    let mut queue: VecDeque<(Piece, Vec<Input>)> = VecDeque::new();
+
+    seen.insert((start.position.x, start.position.y, start.rotation));
+    queue.push_back((start, vec![]));
+    while let Some((current, inputs)) = queue.pop_front() {
+        let landed = apply(board, &current, Input::HardDrop).unwrap_or(current);
+        let key = landing_key(&landed);
+        if landings.insert(key) {
+            let mut path = inputs.clone();
+            path.push(Input::HardDrop);
+            placements.push(Placement {
+                piece: landed,
+                inputs: path,
+            });
+        }
+
+        for input in MOVES {
+            if let Some(next) = apply(board, &current, input) {
+                if seen.insert((next.position.x, next.position.y, next.rotation)) {
+                    let mut path = inputs.clone();
+                    path.push(input);
+                    queue.push_back((next, path));
+                }
+            }
+        }
+    }
+    placements
+}
+
+// Counts the leaf positions reached by placing each kind in turn, clearing
+// lines in between, like a chess perft.
+pub fn perft(board: &Board, kinds: &[Kind]) -> usize {
+    match kinds.split_first() {
+        None => 1,
+        Some((kind, rest)) => reachable_placements(board, &Piece::spawn(*kind))
+            .iter()
+            .map(|placement| {
+                if rest.is_empty() {
+                    return 1;
+                }
+                let mut next = board.clone();
+                match next.place(&placement.piece) {
+                    Ok(_) => perft(&next, rest),
+                    Err(_) => 0,
+                }
+            })
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::piece::Spin;
+    use super::*;
+
+    #[test]
+    fn perft_empty_board() {
+        let board = Board::blank();
+        let counts = [
+            (Kind::O, 9),
+            (Kind::I, 17),
+            (Kind::T, 34),
+            (Kind::L, 34),
+            (Kind::J, 34),
+            (Kind::S, 17),
+            (Kind::Z, 17),
+        ];
+        for (kind, count) in counts {
+            assert_eq!(perft(&board, &[kind]), count, "{}", kind);
+        }
+        assert_eq!(perft(&board, &[Kind::T, Kind::I]), 600);
+        assert_eq!(perft(&board, &[Kind::I, Kind::O]), 153);
+    }
+
+    #[test]
+    fn kicks_into_t_spin_double() {
+        let board = Board::from_rows(&["...XXXXXXX", "X...XXXXXX", "XX.XXXXXXX"]);
+        let placement = reachable_placements(&board, &Piece::spawn(Kind::T))
+            .into_iter()
+            .find(|placement| placement.key() == [(1, 18), (2, 18), (2, 19), (3, 18)])
+            .expect("slot under the overhang is reachable");
+        let turn = placement.inputs[placement.inputs.len() - 2];
+        assert!(matches!(
+            turn,
+            Input::Move(Direction::CW | Direction::CCW | Direction::FLIP)
+        ));
+        assert_eq!(placement.piece.t_spin(&board), Spin::Full);
+        let mut board = board;
+        assert_eq!(board.place(&placement.piece), Ok(2));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::ai::{Ai, Planner};
+use crate::engine::piece::Kind;
+use crate::engine::placement::{Input, Placement};
+use crate::engine::{reachable_placements, Board, Coordinate, Engine};
+use crate::input::{Frame, InputSource};
+use crate::sim;
+
+// A gym-style wrapper around a simulated engine for training agents.
+
+const ROWS: usize = Board::HEIGHT as usize;
+const COLUMNS: usize = Board::WIDTH as usize;
+// Enough frames to get through any lock delay and line clear animation.
+const MAX_FRAMES_PER_PIECE: usize = 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    // Applied before a single frame of the game runs.
+    Input(Input),
+    // Index into placements(), played out until the next piece appears.
+    Place(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub board: [[bool; COLUMNS]; ROWS],
+    // Cells of the falling piece, if there is one.
+    pub active: Vec<Coordinate>,
+    pub current: Option<Kind>,
+    pub next: Vec<Kind>,
+    pub level: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Info {
+    pub lines: usize,
+    pub points: usize,
+    pub pieces: usize,
+    pub frames: usize,
+}
+
+pub struct Env {
+    engine: Engine,
+    done: bool,
+    frames: usize,
+}
+
+// Hands the executor one chosen placement.
+struct Chosen(Option<Placement>);
+
+impl Planner for Chosen {
+    fn plan(&mut self, _engine: &Engine) -> Option<Placement> {
+        self.0.take()
+    }
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Env {
+            engine: Engine::simulated(0),
+            done: true,
+            frames: 0,
+        }
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.engine = Engine::simulated(seed);
+        self.engine.place_cursor();
+        self.done = false;
+        self.frames = 0;
+        self.observe()
+    }
+
+    // Final placements the active piece can reach, for Action::Place.
+    pub fn placements(&self) -> Vec<Placement> {
+        match &self.engine.cursor {
+            Some(cursor) => reachable_placements(self.engine.board(), cursor),
+            None => vec![],
+        }
+    }
+
+    // The reward is the score gained during the step; an out of range
+    // placement hard drops the piece where it is.
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool, Info) {
+        let lines = self.engine.rows_cleared;
+        let points = self.engine.points;
+        let pieces = self.engine.pieces;
+        let frames = self.frames;
+
+        if !self.done {
+            match action {
+                Action::Input(input) => {
+                    let frame = match input {
+                        Input::Move(direction) => Frame {
+                            moves: vec![direction],
+                            ..Frame::default()
+                        },
+                        Input::SoftDrop => Frame {
+                            soft_drop: true,
+                            ..Frame::default()
+                        },
+                        Input::HardDrop => Frame {
+                            hard_drop: true,
+                            ..Frame::default()
+                        },
+                    };
+                    self.frame(frame);
+                }
+                Action::Place(index) => self.place(self.placements().into_iter().nth(index)),
+            }
+        }
+
+        let info = Info {
+            lines: self.engine.rows_cleared - lines,
+            points: self.engine.points - points,
+            pieces: self.engine.pieces - pieces,
+            frames: self.frames - frames,
+        };
+        (self.observe(), info.points as f64, self.done, info)
+    }
+
+    fn frame(&mut self, frame: Frame) {
+        self.frames += 1;
+        if sim::step(&mut self.engine, frame).is_err() {
+            self.done = true;
+        }
+    }
+
+    fn place(&mut self, placement: Option<Placement>) {
+        let mut driver = Ai::with_planner(Box::new(Chosen(placement)));
+        let pieces = self.engine.pieces;
+        for _ in 0..MAX_FRAMES_PER_PIECE {
+            if self.done || (self.engine.pieces > pieces && self.engine.cursor.is_some()) {
+                break;
+            }
+            let frame = driver.frame(&self.engine);
+            self.frame(frame);
+        }
+    }
+
+    fn observe(&self) -> Observation {
+        let mut board = [[false; COLUMNS]; ROWS];
+        for (y, row) in board.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = self
+                    .engine
+                    .board()
+                    .filled(Coordinate::new(x as isize, y as isize));
+            }
+        }
+        Observation {
+            board,
+            active: self
+                .engine
+                .cursor
+                .as_ref()
+                .map_or(vec![], |cursor| cursor.cells()),
+            current: self.engine.cursor.as_ref().map(|cursor| cursor.kind),
+            next: self.engine.queue.iter().copied().collect(),
+            level: self.engine.level,
+        }
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_plays_the_same_episode() {
+        let mut first = Env::new();
+        let mut second = Env::new();
+        let a = first.reset(7);
+        let b = second.reset(7);
+        assert_eq!((a.current, a.next), (b.current, b.next));
+        for step in 0..20 {
+            let a = first.step(Action::Place(step % 5));
+            let b = second.step(Action::Place(step % 5));
+            assert_eq!(a.0.board, b.0.board);
+            assert_eq!((a.0.current, &a.0.next), (b.0.current, &b.0.next));
+            assert_eq!((a.1, a.2), (b.1, b.2));
+        }
+    }
+
+    #[test]
+    fn clearing_a_line_is_rewarded_with_its_score() {
+        let mut env = Env::new();
+        env.reset(0);
+        env.engine.add_garbage(&[0]).unwrap();
+        let placement = env
+            .placements()
+            .into_iter()
+            .find(|placement| placement.key().contains(&(0, Board::HEIGHT - 1)))
+            .expect("the piece fits the hole");
+        let (drop, moves) = placement.inputs.split_last().unwrap();
+        assert_eq!(*drop, Input::HardDrop);
+        for input in moves {
+            let (_, reward, _, _) = env.step(Action::Input(*input));
+            assert_eq!(reward, 0.0);
+        }
+
+        let mut piece = env.engine().cursor.unwrap();
+        let mut height = 0;
+        while piece.can_lower(env.engine().board()) {
+            piece = piece.lower();
+            height += 1;
+        }
+        let (_, reward, done, info) = env.step(Action::Input(Input::HardDrop));
+        // A single at level 1 plus two points a row for the hard drop.
+        assert_eq!(reward, (100 + 2 * height) as f64);
+        assert_eq!(info.points, 100 + 2 * height);
+        assert!(!done);
+    }
+
+    #[test]
+    fn topping_out_ends_the_episode() {
+        let mut env = Env::new();
+        env.reset(3);
+        let mut done = false;
+        for _ in 0..100 {
+            done = env.step(Action::Place(0)).2;
+            if done {
+                break;
+            }
+        }
+        assert!(done);
+        let (_, reward, done, info) = env.step(Action::Place(0));
+        assert!(done);
+        assert_eq!(reward, 0.0);
+        assert_eq!(info.frames, 0);
+    }
+}
@@ -0,0 +1,112 @@
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+use tetris::engine::Engine;
+use tetris::input::{Action, Buttons, Frame, Handling, InputSource};
+
+// Every plugged in game controller drives the game, and controllers can
+// come and go while it runs.
+pub struct Gamepad {
+    subsystem: GameControllerSubsystem,
+    controllers: Vec<GameController>,
+    buttons: Buttons,
+    start: bool,
+    start_pressed: bool,
+}
+
+impl Gamepad {
+    const BUTTONS: [(Button, Action); 10] = [
+        (Button::DPadLeft, Action::Left),
+        (Button::DPadRight, Action::Right),
+        (Button::DPadDown, Action::SoftDrop),
+        (Button::DPadUp, Action::HardDrop),
+        (Button::A, Action::RotateCw),
+        (Button::X, Action::RotateCw),
+        (Button::B, Action::RotateCcw),
+        (Button::Y, Action::Rotate180),
+        (Button::RightShoulder, Action::RotateCw),
+        (Button::LeftShoulder, Action::RotateCcw),
+    ];
+    // Stick travel, out of 32767, that is ignored around the centre.
+    const DEADZONE: i16 = 12000;
+
+    pub fn new(subsystem: GameControllerSubsystem, handling: Handling) -> Self {
+        let mut gamepad = Gamepad {
+            subsystem,
+            controllers: vec![],
+            buttons: Buttons::new(handling),
+            start: false,
+            start_pressed: false,
+        };
+        for index in 0..gamepad.subsystem.num_joysticks().unwrap_or(0) {
+            gamepad.add(index);
+        }
+        gamepad
+    }
+
+    fn add(&mut self, index: u32) {
+        if !self.subsystem.is_game_controller(index) {
+            return;
+        }
+        // One that can't be opened is left out, as if not plugged in.
+        if let Ok(controller) = self.subsystem.open(index) {
+            let id = controller.instance_id();
+            if self.controllers.iter().all(|c| c.instance_id() != id) {
+                self.controllers.push(controller);
+            }
+        }
+    }
+
+    pub fn set_handling(&mut self, handling: Handling) {
+        self.buttons.handling = handling;
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::ControllerDeviceAdded { which, .. } => self.add(*which),
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.controllers.retain(|c| c.instance_id() != *which)
+            }
+            _ => (),
+        }
+    }
+
+    // Start, which pauses in a game, also starts one from the menus.
+    pub fn start_pressed(&self) -> bool {
+        self.start_pressed
+    }
+
+    pub fn update(&mut self) {
+        let mut down = vec![];
+        let mut start = false;
+        for controller in &self.controllers {
+            for (button, action) in Gamepad::BUTTONS {
+                if controller.button(button) {
+                    down.push(action);
+                }
+            }
+            let x = controller.axis(Axis::LeftX);
+            if x < -Gamepad::DEADZONE {
+                down.push(Action::Left);
+            } else if x > Gamepad::DEADZONE {
+                down.push(Action::Right);
+            }
+            if controller.axis(Axis::LeftY) > Gamepad::DEADZONE {
+                down.push(Action::SoftDrop);
+            }
+            start |= controller.button(Button::Start);
+        }
+        if start {
+            down.push(Action::Pause);
+        }
+        self.start_pressed = start && !self.start;
+        self.start = start;
+        self.buttons.set_down(down);
+    }
+}
+
+impl InputSource for Gamepad {
+    fn frame(&mut self, engine: &Engine) -> Frame {
+        self.buttons.frame(engine)
+    }
+}
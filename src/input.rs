@@ -0,0 +1,724 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::engine::piece::Direction;
+use crate::engine::{Board, ClearGravity, ClearStyle, Engine, Progression, SoftDrop, Timing};
+use crate::mode::Mode;
+use crate::sim::FRAME;
+
+// What a player, bot or recording asks of the game on a single frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frame {
+    pub moves: Vec<Direction>,
+    pub hard_drop: bool,
+    pub soft_drop: bool,
+    pub pause: bool,
+}
+
+impl Frame {
+    // Combines the input of several sources driving the same game.
+    pub fn merge(&mut self, other: Frame) {
+        self.moves.extend(other.moves);
+        self.hard_drop |= other.hard_drop;
+        self.soft_drop |= other.soft_drop;
+        self.pause |= other.pause;
+    }
+
+    fn tokens(&self) -> Vec<&'static str> {
+        let mut tokens: Vec<&'static str> = self
+            .moves
+            .iter()
+            .map(|direction| match direction {
+                Direction::LEFT => "L",
+                Direction::RIGHT => "R",
+                Direction::CW => "CW",
+                Direction::CCW => "CCW",
+                Direction::FLIP => "180",
+            })
+            .collect();
+        if self.soft_drop {
+            tokens.push("soft");
+        }
+        if self.hard_drop {
+            tokens.push("drop");
+        }
+        if self.pause {
+            tokens.push("pause");
+        }
+        tokens
+    }
+}
+
+pub trait InputSource {
+    fn frame(&mut self, engine: &Engine) -> Frame;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Left,
+    Right,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    SoftDrop,
+    HardDrop,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Left,
+        Action::Right,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Rotate180,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::Pause,
+    ];
+
+    // As written in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::RotateCw => "rotate_cw",
+            Action::RotateCcw => "rotate_ccw",
+            Action::Rotate180 => "rotate_180",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::Pause => "pause",
+        }
+    }
+}
+
+// How a held shift repeats and how fast soft drop falls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Handling {
+    // Delayed auto shift: how long a shift is held before it repeats.
+    pub das: Duration,
+    // Auto repeat rate: time between repeated shifts, zero for straight
+    // to the wall.
+    pub arr: Duration,
+    // DAS cut delay: how long repeating holds off after a rotation.
+    pub dcd: Duration,
+    pub soft_drop: SoftDrop,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: Duration::from_millis(300),
+            arr: Duration::from_millis(28),
+            dcd: Duration::ZERO,
+            soft_drop: SoftDrop::default(),
+        }
+    }
+}
+
+impl Handling {
+    // One "name = value" per line, times in milliseconds and the soft drop
+    // factor as a number or inf; anything not given keeps its default.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut handling = Handling::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or(format!("{}: expected name = value", line))?;
+            let value = value.trim();
+            let millis = || {
+                value
+                    .parse()
+                    .map(Duration::from_millis)
+                    .map_err(|_| format!("{}: not a number of milliseconds", line))
+            };
+            match name.trim() {
+                "das" => handling.das = millis()?,
+                "arr" => handling.arr = millis()?,
+                "dcd" => handling.dcd = millis()?,
+                "sdf" => handling.soft_drop = value.parse()?,
+                other => return Err(format!("unknown setting {:?}", other)),
+            }
+        }
+        Ok(handling)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = format!(
+            "das = {}\narr = {}\ndcd = {}\nsdf = {}\n",
+            self.das.as_millis(),
+            self.arr.as_millis(),
+            self.dcd.as_millis(),
+            self.soft_drop
+        );
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AutoRepeat {
+    NoPress,
+    Pressed(Action, Instant),
+    Repeating(Action, Instant),
+}
+
+// Turns the actions held down on a device into frames: presses rotate,
+// drop and pause once, soft drop lasts while held and a held shift
+// repeats after a delay. Holding keeps working across the delay between
+// pieces; there is no hold piece, so nothing like IHS.
+pub struct Buttons {
+    pub handling: Handling,
+    down: HashSet<Action>,
+    previous: HashSet<Action>,
+    auto_repeat: AutoRepeat,
+    cut_until: Option<Instant>,
+    had_piece: bool,
+}
+
+impl Buttons {
+    pub fn new(handling: Handling) -> Self {
+        Buttons {
+            handling,
+            down: HashSet::new(),
+            previous: HashSet::new(),
+            auto_repeat: AutoRepeat::NoPress,
+            cut_until: None,
+            had_piece: false,
+        }
+    }
+
+    // The actions held for the next frame.
+    pub fn set_down(&mut self, down: impl IntoIterator<Item = Action>) {
+        self.down = down.into_iter().collect();
+    }
+
+    fn direction(action: Action) -> Direction {
+        match action {
+            Action::Left => Direction::LEFT,
+            _ => Direction::RIGHT,
+        }
+    }
+
+    // With no repeat delay the piece goes all the way to the wall.
+    fn repeat(&self, action: Action) -> Vec<Direction> {
+        let count = if self.handling.arr.is_zero() {
+            Board::WIDTH as usize
+        } else {
+            1
+        };
+        vec![Buttons::direction(action); count]
+    }
+
+    fn update(&mut self, now: Instant, has_piece: bool) -> Frame {
+        let pressed: HashSet<Action> = &self.down - &self.previous;
+        let spawned = has_piece && !self.had_piece;
+        self.had_piece = has_piece;
+        let mut frame = Frame {
+            soft_drop: self.down.contains(&Action::SoftDrop),
+            hard_drop: pressed.contains(&Action::HardDrop),
+            pause: pressed.contains(&Action::Pause),
+            ..Frame::default()
+        };
+        // A rotation held through the entry delay turns the new piece as it
+        // appears (IRS).
+        let mut rotated = false;
+        for (action, direction) in [
+            (Action::RotateCcw, Direction::CCW),
+            (Action::RotateCw, Direction::CW),
+            (Action::Rotate180, Direction::FLIP),
+        ] {
+            if pressed.contains(&action) {
+                frame.moves.push(direction);
+                rotated = true;
+            } else if spawned && self.down.contains(&action) {
+                frame.moves.push(direction);
+            }
+        }
+        if rotated && !self.handling.dcd.is_zero() {
+            self.cut_until = Some(now + self.handling.dcd);
+        }
+        let cut = self.cut_until.is_some_and(|until| now < until);
+
+        let shift = [Action::Left, Action::Right]
+            .into_iter()
+            .find(|action| pressed.contains(action));
+        match (shift, self.auto_repeat) {
+            (Some(action), _) => {
+                frame.moves.push(Buttons::direction(action));
+                self.auto_repeat = AutoRepeat::Pressed(action, now);
+            }
+            (None, AutoRepeat::NoPress) => (),
+            (None, AutoRepeat::Pressed(action, _) | AutoRepeat::Repeating(action, _))
+                if !self.down.contains(&action) =>
+            {
+                self.auto_repeat = AutoRepeat::NoPress;
+            }
+            (None, AutoRepeat::Pressed(action, start)) => {
+                if now - start >= self.handling.das && !cut {
+                    frame.moves.extend(self.repeat(action));
+                    self.auto_repeat = AutoRepeat::Repeating(action, now);
+                } else if spawned {
+                    // Held but not charged yet: a single initial shift.
+                    frame.moves.push(Buttons::direction(action));
+                }
+            }
+            // The charge survives between pieces, so a new piece starts
+            // shifting straight away.
+            (None, AutoRepeat::Repeating(action, start)) => {
+                if (now - start >= self.handling.arr || spawned) && !cut {
+                    frame.moves.extend(self.repeat(action));
+                    self.auto_repeat = AutoRepeat::Repeating(action, now);
+                }
+            }
+        }
+
+        self.previous = self.down.clone();
+        frame
+    }
+}
+
+impl Default for Buttons {
+    fn default() -> Self {
+        Self::new(Handling::default())
+    }
+}
+
+impl InputSource for Buttons {
+    fn frame(&mut self, engine: &Engine) -> Frame {
+        self.update(engine.clock.now(), engine.cursor.is_some())
+    }
+}
+
+// Writes every frame played to a file, runs of identical frames on one
+// line, so a game on a seeded, simulated engine can be played back.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    last: Option<(usize, Frame)>,
+}
+
+impl Recorder {
+    // The engine's rules and the mode's settings are written ahead of the
+    // frames.
+    pub fn create(
+        path: &Path,
+        seed: u64,
+        engine: &Engine,
+        mode: &dyn Mode,
+    ) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(
+            writer,
+            "seed {}\nsdf {}\ntiming {}\nclear {}\ngravity {}\nlevel {}\nprogression {}",
+            seed,
+            engine.soft_drop,
+            engine.timing,
+            engine.clear_style,
+            engine.clear_gravity,
+            engine.start_level,
+            engine.progression
+        )
+        .map_err(|e| e.to_string())?;
+        let mut line = format!("mode {}", mode.name());
+        for (name, value) in mode.settings() {
+            line.push_str(&format!(" {}={}", name, value));
+        }
+        writeln!(writer, "{}", line).map_err(|e| e.to_string())?;
+        Ok(Recorder { writer, last: None })
+    }
+
+    pub fn record(&mut self, frame: &Frame) {
+        match &mut self.last {
+            Some((count, last)) if last == frame => *count += 1,
+            _ => {
+                self.write_last();
+                self.last = Some((1, frame.clone()));
+            }
+        }
+    }
+
+    fn write_last(&mut self) {
+        if let Some((count, frame)) = self.last.take() {
+            let mut line = count.to_string();
+            for token in frame.tokens() {
+                line.push(' ');
+                line.push_str(token);
+            }
+            let _ = writeln!(self.writer, "{}", line);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.write_last();
+        let _ = self.writer.flush();
+    }
+}
+
+pub struct Replay {
+    pub seed: u64,
+    pub soft_drop: SoftDrop,
+    pub timing: Timing,
+    pub clear_style: ClearStyle,
+    pub clear_gravity: ClearGravity,
+    pub start_level: usize,
+    pub progression: Progression,
+    // The mode played and its settings, if the replay says.
+    pub mode: Option<String>,
+    pub mode_settings: Vec<(String, String)>,
+    frames: Vec<(usize, Frame)>,
+    position: usize,
+    repeated: usize,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Replay::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut seed = None;
+        let mut soft_drop = SoftDrop::default();
+        let mut timing = Timing::default();
+        let mut clear_style = ClearStyle::default();
+        let mut clear_gravity = ClearGravity::default();
+        let mut start_level = 1;
+        let mut progression = Progression::default();
+        let mut mode = None;
+        let mut mode_settings = vec![];
+        let mut frames = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |what: &str| format!("line {}: {}", number + 1, what);
+            let mut tokens = line.split_whitespace();
+            let first = tokens.next().unwrap_or_default();
+            if first == "seed" {
+                let value = tokens.next().ok_or_else(|| error("seed needs a value"))?;
+                seed = Some(value.parse().map_err(|_| error("seed is not a number"))?);
+                continue;
+            }
+            if first == "sdf" {
+                let value = tokens.next().ok_or_else(|| error("sdf needs a value"))?;
+                soft_drop = value.parse().map_err(|e: String| error(&e))?;
+                continue;
+            }
+            if first == "timing" {
+                let value = tokens.next().ok_or_else(|| error("timing needs a value"))?;
+                timing = value.parse().map_err(|e: String| error(&e))?;
+                continue;
+            }
+            if first == "clear" {
+                let value = tokens.next().ok_or_else(|| error("clear needs a value"))?;
+                clear_style = value.parse().map_err(|e: String| error(&e))?;
+                continue;
+            }
+            if first == "gravity" {
+                let value = tokens
+                    .next()
+                    .ok_or_else(|| error("gravity needs a value"))?;
+                clear_gravity = value.parse().map_err(|e: String| error(&e))?;
+                continue;
+            }
+            if first == "level" {
+                let value = tokens.next().ok_or_else(|| error("level needs a value"))?;
+                start_level = value.parse().map_err(|_| error("level is not a number"))?;
+                continue;
+            }
+            if first == "progression" {
+                let value = tokens
+                    .next()
+                    .ok_or_else(|| error("progression needs a value"))?;
+                progression = value.parse().map_err(|e: String| error(&e))?;
+                continue;
+            }
+            if first == "mode" {
+                let value = tokens.next().ok_or_else(|| error("mode needs a name"))?;
+                mode = Some(value.to_string());
+                for setting in tokens {
+                    let (name, value) = setting
+                        .split_once('=')
+                        .ok_or_else(|| error("expected name=value after the mode"))?;
+                    mode_settings.push((name.to_string(), value.to_string()));
+                }
+                continue;
+            }
+            let count: usize = first.parse().map_err(|_| error("expected a frame count"))?;
+            let mut frame = Frame::default();
+            for token in tokens {
+                match token {
+                    "L" => frame.moves.push(Direction::LEFT),
+                    "R" => frame.moves.push(Direction::RIGHT),
+                    "CW" => frame.moves.push(Direction::CW),
+                    "CCW" => frame.moves.push(Direction::CCW),
+                    "180" => frame.moves.push(Direction::FLIP),
+                    "soft" => frame.soft_drop = true,
+                    "drop" => frame.hard_drop = true,
+                    "pause" => frame.pause = true,
+                    _ => return Err(error(&format!("unknown input {:?}", token))),
+                }
+            }
+            frames.push((count, frame));
+        }
+        Ok(Replay {
+            seed: seed.ok_or("replay has no seed")?,
+            soft_drop,
+            timing,
+            clear_style,
+            clear_gravity,
+            start_level,
+            progression,
+            mode,
+            mode_settings,
+            frames,
+            position: 0,
+            repeated: 0,
+        })
+    }
+
+    // Gives the mode the settings it was played with.
+    pub fn set_up(&self, mode: &mut dyn Mode) -> Result<(), String> {
+        for (name, value) in &self.mode_settings {
+            mode.set(name, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames: usize = self.frames.iter().map(|(count, _)| count).sum();
+        FRAME * frames as u32
+    }
+
+    pub fn finished(&self) -> bool {
+        self.position >= self.frames.len()
+    }
+}
+
+impl InputSource for Replay {
+    fn frame(&mut self, _engine: &Engine) -> Frame {
+        let Some((count, frame)) = self.frames.get(self.position) else {
+            return Frame::default();
+        };
+        let frame = frame.clone();
+        self.repeated += 1;
+        if self.repeated >= *count {
+            self.position += 1;
+            self.repeated = 0;
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::piece::{Kind, Rotation};
+    use crate::engine::Coordinate;
+    use crate::mode::{Dig, Survival};
+    use crate::sim::{self, RandomMoves};
+
+    // Plays a mode the way the game does, recording it if given a path,
+    // and returns the engine where the game stopped.
+    fn play(
+        seed: u64,
+        mode: &mut dyn Mode,
+        source: &mut dyn InputSource,
+        record: Option<&Path>,
+    ) -> Engine {
+        let mut engine = Engine::simulated(seed);
+        mode.start(&mut engine);
+        let mut recorder = record.map(|path| Recorder::create(path, seed, &engine, mode).unwrap());
+        engine.place_cursor();
+        for _ in 0..3600 {
+            let frame = source.frame(&engine);
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&frame);
+            }
+            match sim::step(&mut engine, frame).and_then(|_| mode.update(&mut engine)) {
+                Ok(false) => (),
+                _ => break,
+            }
+        }
+        engine
+    }
+
+    fn cells(engine: &Engine) -> Vec<(bool, Option<Kind>)> {
+        (0..Board::HEIGHT)
+            .flat_map(|y| (0..Board::WIDTH).map(move |x| Coordinate::new(x, y)))
+            .map(|coord| (engine.board().filled(coord), engine.board().kind(coord)))
+            .collect()
+    }
+
+    #[test]
+    fn dig_replays_with_its_garbage() {
+        let path = std::env::temp_dir().join(format!("tetris-dig-{}.replay", std::process::id()));
+        let mut dig = Dig::default();
+        dig.rows = 6;
+        dig.messiness = 8;
+        let played = play(11, &mut dig, &mut RandomMoves::new(11), Some(&path));
+
+        let mut replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.mode.as_deref(), Some("dig"));
+        let mut dig = Dig::default();
+        replay.set_up(&mut dig).unwrap();
+        assert_eq!((dig.rows, dig.messiness), (6, 8));
+        let seed = replay.seed;
+        let replayed = play(seed, &mut dig, &mut replay, None);
+
+        assert!(played.pieces > 10);
+        assert!(played.board().garbage_rows() > 0);
+        assert_eq!(cells(&replayed), cells(&played));
+        assert_eq!(replayed.pieces, played.pieces);
+        assert_eq!(replayed.points, played.points);
+    }
+
+    #[test]
+    fn survival_replays_with_its_rising_garbage() {
+        let path =
+            std::env::temp_dir().join(format!("tetris-survival-{}.replay", std::process::id()));
+        let mut survival = Survival::default();
+        survival.interval = 2;
+        survival.messiness = 9;
+        let played = play(5, &mut survival, &mut RandomMoves::new(5), Some(&path));
+
+        let mut replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.mode.as_deref(), Some("survival"));
+        let mut survival = Survival::default();
+        replay.set_up(&mut survival).unwrap();
+        assert_eq!((survival.interval, survival.messiness), (2, 9));
+        let seed = replay.seed;
+        let replayed = play(seed, &mut survival, &mut replay, None);
+
+        assert!(played.board().garbage_rows() > 0);
+        assert_eq!(cells(&replayed), cells(&played));
+        assert_eq!(replayed.pieces, played.pieces);
+        assert_eq!(replayed.points, played.points);
+    }
+
+    fn handling(das: u32, arr: u32, dcd: u32) -> Handling {
+        Handling {
+            das: FRAME * das,
+            arr: FRAME * arr,
+            dcd: FRAME * dcd,
+            ..Handling::default()
+        }
+    }
+
+    // Holds the given actions for a number of frames on the engine's
+    // simulated clock, returning the moves asked for on each frame.
+    fn hold(
+        buttons: &mut Buttons,
+        engine: &mut Engine,
+        down: &[Action],
+        frames: usize,
+    ) -> Vec<Vec<Direction>> {
+        (0..frames)
+            .map(|_| {
+                buttons.set_down(down.iter().copied());
+                let frame = buttons.frame(engine);
+                let moves = frame.moves.clone();
+                sim::step(engine, frame).unwrap();
+                moves
+            })
+            .collect()
+    }
+
+    fn at_right_wall(engine: &Engine) -> bool {
+        let cursor = engine.cursor.as_ref().unwrap();
+        cursor.cells().iter().any(|c| c.x == Board::WIDTH - 1)
+    }
+
+    #[test]
+    fn held_shift_moves_at_once_then_repeats_after_das() {
+        let mut engine = Engine::simulated(1);
+        engine.place_cursor();
+        let mut buttons = Buttons::new(handling(6, 2, 0));
+        let moves = hold(&mut buttons, &mut engine, &[Action::Right], 13);
+        for (frame, moves) in moves.iter().enumerate() {
+            let expected = match frame {
+                0 | 6 | 8 | 10 | 12 => vec![Direction::RIGHT],
+                _ => vec![],
+            };
+            assert_eq!(*moves, expected, "frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn zero_arr_shifts_to_the_wall() {
+        let mut engine = Engine::simulated(1);
+        engine.place_cursor();
+        let mut buttons = Buttons::new(handling(6, 0, 0));
+        let moves = hold(&mut buttons, &mut engine, &[Action::Right], 7);
+        assert_eq!(moves[0], [Direction::RIGHT]);
+        assert!(moves[1..6].iter().all(|moves| moves.is_empty()));
+        assert_eq!(moves[6], [Direction::RIGHT; Board::WIDTH as usize]);
+        assert!(at_right_wall(&engine));
+    }
+
+    #[test]
+    fn dcd_pauses_auto_repeat_after_a_rotation() {
+        let mut engine = Engine::simulated(1);
+        engine.place_cursor();
+        let mut buttons = Buttons::new(handling(6, 2, 6));
+        let charged = hold(&mut buttons, &mut engine, &[Action::Right], 9);
+        assert_eq!(charged[8], [Direction::RIGHT]);
+        let moves = hold(
+            &mut buttons,
+            &mut engine,
+            &[Action::Right, Action::RotateCw],
+            7,
+        );
+        assert_eq!(moves[0], [Direction::CW]);
+        assert!(moves[1..6].iter().all(|moves| moves.is_empty()));
+        assert_eq!(moves[6], [Direction::RIGHT]);
+    }
+
+    #[test]
+    fn rotation_held_through_entry_delay_turns_the_new_piece() {
+        let mut engine = Engine::simulated(1);
+        engine.place_cursor();
+        let mut buttons = Buttons::new(handling(6, 2, 0));
+        hold(&mut buttons, &mut engine, &[Action::HardDrop], 1);
+        assert!(engine.cursor.is_none());
+        while engine.cursor.is_none() {
+            hold(&mut buttons, &mut engine, &[Action::RotateCw], 1);
+        }
+        let moves = hold(&mut buttons, &mut engine, &[Action::RotateCw], 1);
+        assert_eq!(moves[0], [Direction::CW]);
+        assert_eq!(engine.cursor.unwrap().rotation, Rotation::E);
+    }
+
+    #[test]
+    fn held_shift_keeps_its_charge_across_a_spawn() {
+        let mut engine = Engine::simulated(1);
+        engine.place_cursor();
+        let mut buttons = Buttons::new(handling(6, 0, 0));
+        hold(&mut buttons, &mut engine, &[Action::Right], 7);
+        hold(
+            &mut buttons,
+            &mut engine,
+            &[Action::Right, Action::HardDrop],
+            1,
+        );
+        while engine.cursor.is_none() {
+            hold(&mut buttons, &mut engine, &[Action::Right], 1);
+        }
+        let moves = hold(&mut buttons, &mut engine, &[Action::Right], 1);
+        assert_eq!(moves[0], [Direction::RIGHT; Board::WIDTH as usize]);
+        assert!(at_right_wall(&engine));
+    }
+}
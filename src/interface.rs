@@ -1,7 +1,17 @@
 extern crate sdl2;
 
-use crate::engine::piece::{Direction, Kind, Piece, Rotation};
-use crate::engine::{Board, Coordinate, Engine};
+use crate::control::{ControlServer, Snapshot};
+use crate::gamepad::Gamepad;
+use crate::keyboard::{KeyAction, KeyBindings, Keyboard};
+use tetris::ai::{Ai, Weights};
+use tetris::engine::piece::{Kind, Piece, Rotation};
+use tetris::engine::{
+    Board, ClearGravity, ClearStyle, Coordinate, Engine, Gravity, Progression, SoftDrop, Timing,
+};
+use tetris::input::{Frame, Handling, InputSource, Recorder, Replay};
+use tetris::mode::{Dig, Endless, Marathon, Master, Mode, Sprint, Survival, Ultra};
+use tetris::sim;
+use tetris::tbp::TbpBot;
 use sdl2::keyboard::{Keycode, Scancode};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
@@ -11,13 +21,21 @@ use sdl2::EventPump;
 use sdl2::{event::Event, render::WindowCanvas};
 use std::cmp;
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 enum GameState {
-    TitleScreen,
+    TitleScreen(Instant),
+    // The selected row, and whether a key to bind to it is awaited.
+    Settings(usize, bool),
+    // Picking the mode's options before a game, with the selected row.
+    ModeSetup(usize),
+    Demo,
     Playing,
     Paused,
     GameOver,
+    // The mode's goal was reached.
+    Finished,
 }
 
 #[non_exhaustive]
@@ -27,6 +45,7 @@ impl Colors {
     pub const BG: Color = Color::RGB(128, 128, 255);
     pub const LIVE_AREA: Color = Color::RGB(187, 183, 190);
     pub const MARKED_CELL: Color = Color::RGB(255, 100, 100);
+    pub const GARBAGE: Color = Color::RGB(150, 150, 150);
     pub const PAL1_1: Color = Color::RGB(101, 187, 249);
     pub const PAL1_2: Color = Color::RGB(35, 86, 237);
     pub const PAL1_3: Color = Color::RGB(240, 250, 252);
@@ -242,8 +261,8 @@ impl Matrix {
                 if mino.y <= -1 {
                     let mino_offset = (mino.y - -1) as i32 * Matrix::SQUARE_SIZE; // 0 or 1 squares
                     y = cmp::max(
-                        self.y as i32,
-                        self.y + (pixel_offset_y as i32 - Matrix::TWO_THIRDS) + mino_offset,
+                        self.y,
+                        self.y + (pixel_offset_y - Matrix::TWO_THIRDS) + mino_offset,
                     );
                     height = cmp::min(
                         Matrix::ONE_THIRD + pixel_offset_y + mino_offset,
@@ -274,7 +293,10 @@ impl Matrix {
         }
 
         for cell in engine.get_pile() {
-            let (r, g, b) = Colors::color_for(level, &cell.kind).rgb();
+            let (r, g, b) = match cell.kind {
+                Some(kind) => Colors::color_for(level, &kind).rgb(),
+                None => Colors::GARBAGE.rgb(),
+            };
             let locked_color = Color::RGB(r - 20, g - 20, b - 20);
             canvas.set_draw_color(locked_color);
             canvas
@@ -331,27 +353,448 @@ impl Matrix {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum AutoRepeat {
-    NoPress,
-    Pressed(Scancode, Instant),
-    Repeating(Scancode, Instant),
+// Colour and place of a line of text.
+#[derive(Clone, Copy)]
+struct TextStyle {
+    color: Color,
+    x: u32,
+    y: u32,
+    // Across the middle of the window, y then counting from its centre.
+    centered: bool,
+}
+
+impl TextStyle {
+    fn at(color: Color, x: u32, y: u32) -> Self {
+        TextStyle {
+            color,
+            x,
+            y,
+            centered: false,
+        }
+    }
+
+    fn centered(color: Color, y: u32) -> Self {
+        TextStyle {
+            color,
+            x: 0,
+            y,
+            centered: true,
+        }
+    }
 }
 
 pub struct Interface {
     state: GameState,
     pressed_keys: HashSet<Scancode>,
-    auto_repeat: AutoRepeat,
-    soft_drop: bool,
+    keyboard: Keyboard,
+    handling: Handling,
+    timing: Timing,
+    clear_style: ClearStyle,
+    clear_gravity: ClearGravity,
+    gamepad: Option<Gamepad>,
+    demo: Ai,
+    weights: Weights,
+    bot: Option<String>,
+    control: Option<ControlServer>,
+    replay: Option<Replay>,
+    record_to: Option<PathBuf>,
+    recorder: Option<Recorder>,
+    modes: Vec<Box<dyn Mode>>,
+    // The mode played, out of modes.
+    mode: usize,
 }
 
 impl Interface {
+    const DEMO_AFTER: Duration = Duration::from_secs(20);
+    const KEYS_FILE: &'static str = "./keys.cfg";
+    const HANDLING_FILE: &'static str = "./handling.cfg";
+    const RECORDS_FILE: &'static str = "./records.cfg";
+    const WEIGHTS_FILE: &'static str = "./weights.cfg";
+    // Rows of the settings screen: a key binding per action, then the
+    // handling values.
+    const SETTINGS: usize = KeyAction::ALL.len() + 4;
+
     pub fn new() -> Self {
+        let handling = Interface::handling();
         Interface {
-            state: GameState::TitleScreen,
+            state: GameState::TitleScreen(Instant::now()),
             pressed_keys: HashSet::new(),
-            auto_repeat: AutoRepeat::NoPress,
-            soft_drop: false,
+            keyboard: Keyboard::new(Interface::key_bindings(), handling),
+            handling,
+            timing: Timing::default(),
+            clear_style: ClearStyle::default(),
+            clear_gravity: ClearGravity::default(),
+            gamepad: None,
+            demo: Ai::new(Weights::default()),
+            weights: Weights::default(),
+            bot: None,
+            control: None,
+            replay: None,
+            record_to: None,
+            recorder: None,
+            modes: vec![
+                Box::new(Endless),
+                Box::new(Marathon::default()),
+                Box::new(Sprint::new(40, PathBuf::from(Interface::RECORDS_FILE))),
+                Box::new(Ultra::new(Duration::from_secs(120))),
+                Box::new(Dig::default()),
+                Box::new(Survival::default()),
+                Box::new(Master::default()),
+            ],
+            mode: 0,
+        }
+    }
+
+    pub fn set_sprint_lines(&mut self, lines: usize) {
+        self.replace_mode(Box::new(Sprint::new(
+            lines,
+            PathBuf::from(Interface::RECORDS_FILE),
+        )));
+    }
+
+    pub fn set_ultra_time(&mut self, duration: Duration) {
+        self.replace_mode(Box::new(Ultra::new(duration)));
+    }
+
+    fn replace_mode(&mut self, mode: Box<dyn Mode>) {
+        if let Some(i) = self.modes.iter().position(|m| m.name() == mode.name()) {
+            self.modes[i] = mode;
+        }
+    }
+
+    pub fn select_mode(&mut self, name: &str) -> Result<(), String> {
+        let names: Vec<&str> = self.modes.iter().map(|mode| mode.name()).collect();
+        self.mode = names.iter().position(|mode| *mode == name).ok_or(format!(
+            "mode must be one of {}: {:?}",
+            names.join(", "),
+            name
+        ))?;
+        Ok(())
+    }
+
+    // Entry and line clear delays of the player's games.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    pub fn set_clear_style(&mut self, style: ClearStyle) {
+        self.clear_style = style;
+    }
+
+    pub fn set_clear_gravity(&mut self, gravity: ClearGravity) {
+        self.clear_gravity = gravity;
+    }
+
+    // Games are played on a seeded engine with a frame-locked clock and
+    // every frame is written to the file.
+    pub fn record(&mut self, path: PathBuf) {
+        self.record_to = Some(path);
+    }
+
+    // Plays a recorded game back from the start; the player can still pause
+    // it and takes over once it runs out. The mode it was played in is
+    // selected and set up as it was.
+    pub fn replay(&mut self, replay: Replay) -> Result<(), String> {
+        if let Some(name) = &replay.mode {
+            self.select_mode(name)?;
+            replay.set_up(self.modes[self.mode].as_mut())?;
+        }
+        self.replay = Some(replay);
+        Ok(())
+    }
+
+    pub fn listen(&mut self, address: &str) -> Result<(), String> {
+        self.control = Some(ControlServer::bind(address)?);
+        Ok(())
+    }
+
+    fn state_name(&self) -> &'static str {
+        match self.state {
+            GameState::TitleScreen(_) | GameState::Settings(..) | GameState::ModeSetup(_) => {
+                "title"
+            }
+            GameState::Demo => "demo",
+            GameState::Playing => "playing",
+            GameState::Paused => "paused",
+            GameState::GameOver => "game_over",
+            GameState::Finished => "finished",
+        }
+    }
+
+    // Plays the demo through a Tetris Bot Protocol process instead of the
+    // built-in AI.
+    pub fn use_bot(&mut self, command: String) {
+        self.bot = Some(command);
+    }
+
+    fn key_bindings() -> KeyBindings {
+        match KeyBindings::load(Path::new(Interface::KEYS_FILE)) {
+            Ok(bindings) => bindings,
+            Err(e) => {
+                if Path::new(Interface::KEYS_FILE).exists() {
+                    eprintln!("Using default keys: {}", e);
+                }
+                KeyBindings::default()
+            }
+        }
+    }
+
+    fn handling() -> Handling {
+        match Handling::load(Path::new(Interface::HANDLING_FILE)) {
+            Ok(handling) => handling,
+            Err(e) => {
+                if Path::new(Interface::HANDLING_FILE).exists() {
+                    eprintln!("Using default handling: {}", e);
+                }
+                Handling::default()
+            }
+        }
+    }
+
+    // Up and down pick a row. On an action return adds a key and backspace
+    // unbinds it; on a handling value left and right change it. Escape
+    // saves and goes back to the title.
+    fn adjust_settings(
+        &mut self,
+        newly_pressed: &HashSet<Scancode>,
+        selected: usize,
+        waiting: bool,
+    ) {
+        if waiting {
+            match newly_pressed.iter().next() {
+                Some(Scancode::Escape) => self.state = GameState::Settings(selected, false),
+                Some(scancode) => {
+                    self.keyboard
+                        .bindings
+                        .bind(KeyAction::ALL[selected], *scancode);
+                    self.state = GameState::Settings(selected, false);
+                }
+                None => (),
+            }
+        } else if newly_pressed.contains(&Scancode::Up) {
+            let selected = (selected + Interface::SETTINGS - 1) % Interface::SETTINGS;
+            self.state = GameState::Settings(selected, false);
+        } else if newly_pressed.contains(&Scancode::Down) {
+            let selected = (selected + 1) % Interface::SETTINGS;
+            self.state = GameState::Settings(selected, false);
+        } else if newly_pressed.contains(&Scancode::Escape) {
+            if let Err(e) = self.keyboard.bindings.save(Path::new(Interface::KEYS_FILE)) {
+                eprintln!("Cannot save keys: {}", e);
+            }
+            if let Err(e) = self.handling.save(Path::new(Interface::HANDLING_FILE)) {
+                eprintln!("Cannot save handling: {}", e);
+            }
+            self.state = GameState::TitleScreen(Instant::now());
+        } else if selected < KeyAction::ALL.len() {
+            if newly_pressed.contains(&Scancode::Return) {
+                self.state = GameState::Settings(selected, true);
+            } else if newly_pressed.contains(&Scancode::Backspace) {
+                self.keyboard.bindings.clear(KeyAction::ALL[selected]);
+            }
+        } else if newly_pressed.contains(&Scancode::Left) {
+            self.adjust_handling(selected - KeyAction::ALL.len(), false);
+        } else if newly_pressed.contains(&Scancode::Right) {
+            self.adjust_handling(selected - KeyAction::ALL.len(), true);
+        }
+    }
+
+    // Up and down pick an option and left and right change it, then start
+    // goes on to the game and escape back to the title.
+    fn adjust_mode(
+        &mut self,
+        engine: &mut Engine,
+        newly_pressed: &HashSet<Scancode>,
+        selected: usize,
+        start: bool,
+    ) {
+        let options = self.modes[self.mode].options().len();
+        if newly_pressed.contains(&Scancode::Space)
+            || newly_pressed.contains(&Scancode::Return)
+            || start
+        {
+            self.start_game(engine);
+        } else if newly_pressed.contains(&Scancode::Escape) {
+            self.state = GameState::TitleScreen(Instant::now());
+        } else if newly_pressed.contains(&Scancode::Up) {
+            self.state = GameState::ModeSetup((selected + options - 1) % options);
+        } else if newly_pressed.contains(&Scancode::Down) {
+            self.state = GameState::ModeSetup((selected + 1) % options);
+        } else if newly_pressed.contains(&Scancode::Left) {
+            self.modes[self.mode].adjust(selected, false);
+        } else if newly_pressed.contains(&Scancode::Right) {
+            self.modes[self.mode].adjust(selected, true);
+        }
+    }
+
+    fn adjust_handling(&mut self, row: usize, up: bool) {
+        const SOFT_DROP: [SoftDrop; 8] = [
+            SoftDrop::Factor(1),
+            SoftDrop::Factor(2),
+            SoftDrop::Factor(5),
+            SoftDrop::Factor(10),
+            SoftDrop::Factor(20),
+            SoftDrop::Factor(40),
+            SoftDrop::Factor(80),
+            SoftDrop::Sonic,
+        ];
+        let step = |value: Duration, millis: u64| {
+            if up {
+                value + Duration::from_millis(millis)
+            } else {
+                value.saturating_sub(Duration::from_millis(millis))
+            }
+        };
+        let handling = &mut self.handling;
+        match row {
+            0 => handling.das = step(handling.das, 10),
+            1 => handling.arr = step(handling.arr, 2),
+            2 => handling.dcd = step(handling.dcd, 10),
+            _ => {
+                let index = SOFT_DROP
+                    .iter()
+                    .position(|sdf| *sdf == handling.soft_drop)
+                    .unwrap_or(4);
+                let index = if up {
+                    cmp::min(index + 1, SOFT_DROP.len() - 1)
+                } else {
+                    index.saturating_sub(1)
+                };
+                handling.soft_drop = SOFT_DROP[index];
+            }
+        }
+        self.keyboard.set_handling(self.handling);
+        if let Some(gamepad) = self.gamepad.as_mut() {
+            gamepad.set_handling(self.handling);
+        }
+    }
+
+    // Weights written by tetris-tune for the built-in AI, the defaults
+    // standing in until there are any.
+    pub fn load_weights(&mut self) -> Result<(), String> {
+        let path = Path::new(Interface::WEIGHTS_FILE);
+        if path.exists() {
+            self.weights = Weights::load(path)?;
+            self.demo = self.builtin_ai();
+        }
+        Ok(())
+    }
+
+    fn builtin_ai(&self) -> Ai {
+        Ai::new(self.weights)
+    }
+
+    fn demo_ai(&self) -> Ai {
+        match self.bot.as_deref().map(TbpBot::spawn) {
+            Some(Ok(bot)) => Ai::with_planner(Box::new(bot)),
+            Some(Err(e)) => {
+                eprintln!("Bot failed to start: {}", e);
+                self.builtin_ai()
+            }
+            None => self.builtin_ai(),
+        }
+    }
+
+    fn start_demo(&mut self, engine: &mut Engine) {
+        engine.clear_board();
+        engine.soft_drop = SoftDrop::default();
+        engine.timing = Timing::default();
+        engine.clear_gravity = ClearGravity::default();
+        engine.gravity = Gravity::default();
+        engine.progression = Progression::default();
+        engine.set_start_level(1);
+        engine.place_cursor();
+        self.demo = self.demo_ai();
+        self.state = GameState::Demo;
+    }
+
+    fn play_demo(&mut self, engine: &mut Engine) {
+        let frame = self.demo.frame(engine);
+        if sim::step(engine, frame).is_err() {
+            self.start_demo(engine);
+        }
+    }
+
+    // Only endless play leaves the level to the player; the other modes
+    // set it themselves.
+    fn level_keys(&self) -> bool {
+        self.modes[self.mode].name() == "endless"
+    }
+
+    fn start_game(&mut self, engine: &mut Engine) {
+        if let Some(replay) = &self.replay {
+            *engine = Engine::simulated(replay.seed);
+            engine.soft_drop = replay.soft_drop;
+            engine.timing = replay.timing;
+            engine.clear_style = replay.clear_style;
+            engine.clear_gravity = replay.clear_gravity;
+            self.modes[self.mode].start(engine);
+            engine.progression = replay.progression;
+            engine.set_start_level(replay.start_level);
+            engine.place_cursor();
+            self.state = GameState::Playing;
+            return;
+        }
+        let seed = self.record_to.as_ref().map(|_| rand::random());
+        match seed {
+            Some(seed) => *engine = Engine::simulated(seed),
+            None => engine.clear_board(),
+        }
+        engine.soft_drop = self.handling.soft_drop;
+        engine.timing = self.timing;
+        engine.clear_gravity = self.clear_gravity;
+        engine.clear_style = self.clear_style;
+        engine.progression = Progression::default();
+        engine.gravity = Gravity::default();
+        engine.set_start_level(1);
+        self.modes[self.mode].start(engine);
+        if let (Some(path), Some(seed)) = (&self.record_to, seed) {
+            self.recorder = Recorder::create(path, seed, engine, self.modes[self.mode].as_ref())
+                .map_err(|e| eprintln!("Cannot record: {}", e))
+                .ok();
+        }
+        engine.place_cursor();
+        self.state = GameState::Playing;
+    }
+
+    // Everything the player's devices and remote clients asked for.
+    fn player_frame(&mut self, engine: &Engine, event_pump: &EventPump) -> Frame {
+        self.keyboard.update(event_pump);
+        let mut frame = self.keyboard.frame(engine);
+        if let Some(gamepad) = self.gamepad.as_mut() {
+            gamepad.update();
+            frame.merge(gamepad.frame(engine));
+        }
+        if let Some(control) = self.control.as_mut() {
+            frame.merge(control.frame(engine));
+        }
+        frame
+    }
+
+    fn play(&mut self, engine: &mut Engine, mut frame: Frame) {
+        if frame.pause {
+            self.state = GameState::Paused;
+            return;
+        }
+        if let Some(replay) = self.replay.as_mut() {
+            frame = replay.frame(engine);
+            if replay.finished() {
+                self.replay = None;
+            }
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(&frame);
+        }
+        let result = sim::step(engine, frame).and_then(|_| self.modes[self.mode].update(engine));
+        match result {
+            Ok(false) => (),
+            Ok(true) => {
+                self.recorder = None;
+                self.state = GameState::Finished;
+            }
+            Err(_) => {
+                self.recorder = None;
+                self.state = GameState::GameOver;
+            }
         }
     }
 
@@ -362,108 +805,70 @@ impl Interface {
     fn handle_input(&mut self, engine: &mut Engine, event_pump: &mut EventPump) {
         let scancodes: HashSet<Scancode> =
             event_pump.keyboard_state().pressed_scancodes().collect();
-
-        self.soft_drop = scancodes.contains(&Scancode::Down);
-
         let newly_pressed: HashSet<Scancode> =
             Interface::get_scancodes(&self.pressed_keys, &scancodes);
         self.pressed_keys = scancodes;
+        let frame = self.player_frame(engine, event_pump);
+        let start = self
+            .gamepad
+            .as_ref()
+            .is_some_and(|gamepad| gamepad.start_pressed());
 
         match self.state {
-            GameState::TitleScreen => {
-                if newly_pressed.contains(&Scancode::Space) {
-                    self.state = GameState::Playing
-                }
-            }
-            GameState::Playing => {
-                if newly_pressed.contains(&Scancode::P) {
-                    self.state = GameState::Paused;
-                }
-                if newly_pressed.contains(&Scancode::Up) {
-                    engine.try_move(Direction::CCW);
-                }
-                if newly_pressed.contains(&Scancode::RCtrl) {
-                    engine.try_move(Direction::CW);
-                }
-                if newly_pressed.contains(&Scancode::Space) {
-                    if let Err(_) = engine.drop() {
-                        self.state = GameState::GameOver;
+            GameState::TitleScreen(since) => {
+                if newly_pressed.contains(&Scancode::Space) || start {
+                    match self.modes[self.mode].options().is_empty() {
+                        true => self.start_game(engine),
+                        false => self.state = GameState::ModeSetup(0),
                     }
-                }
-                if newly_pressed.contains(&Scancode::Left)
-                    || newly_pressed.contains(&Scancode::Right)
+                } else if newly_pressed.contains(&Scancode::M) {
+                    self.mode = (self.mode + 1) % self.modes.len();
+                    self.state = GameState::TitleScreen(Instant::now());
+                } else if newly_pressed.contains(&Scancode::K) {
+                    self.state = GameState::Settings(0, false);
+                } else if newly_pressed.contains(&Scancode::D)
+                    || Instant::now() - since > Interface::DEMO_AFTER
                 {
-                    let direction;
-                    if newly_pressed.contains(&Scancode::Left) {
-                        direction = Direction::LEFT;
-                        self.auto_repeat = AutoRepeat::Pressed(Scancode::Left, Instant::now());
-                    } else {
-                        direction = Direction::RIGHT;
-                        self.auto_repeat = AutoRepeat::Pressed(Scancode::Right, Instant::now());
-                    }
-
-                    engine.try_move(direction)
+                    self.start_demo(engine);
                 }
-
-                let scancodes: HashSet<Scancode> =
-                    event_pump.keyboard_state().pressed_scancodes().collect();
-
-                let direction;
-                if scancodes.contains(&Scancode::Left) {
-                    direction = Direction::LEFT;
+            }
+            GameState::Demo => {
+                if !newly_pressed.is_empty() || start {
+                    engine.clear_board();
+                    engine.place_cursor();
+                    self.state = GameState::TitleScreen(Instant::now());
                 } else {
-                    direction = Direction::RIGHT;
-                }
-                match self.auto_repeat {
-                    AutoRepeat::NoPress => (),
-                    AutoRepeat::Pressed(scancode, start) => {
-                        if !scancodes.contains(&scancode) {
-                            self.auto_repeat = AutoRepeat::NoPress;
-                            return;
-                        }
-                        let duration = Instant::now() - start;
-                        if duration >= Duration::from_millis(300) {
-                            self.auto_repeat = AutoRepeat::Repeating(scancode, Instant::now());
-                            engine.try_move(direction);
-                        }
-                    }
-                    AutoRepeat::Repeating(scancode, start) => {
-                        if !scancodes.contains(&scancode) {
-                            self.auto_repeat = AutoRepeat::NoPress;
-                            return;
-                        }
-                        let duration = Instant::now() - start;
-                        if duration >= Duration::from_millis(28) {
-                            engine.try_move(direction);
-                        }
-                    }
+                    self.play_demo(engine);
                 }
             }
+            GameState::Settings(selected, waiting) => {
+                self.adjust_settings(&newly_pressed, selected, waiting)
+            }
+            GameState::ModeSetup(selected) => {
+                self.adjust_mode(engine, &newly_pressed, selected, start)
+            }
+            GameState::Playing => self.play(engine, frame),
             GameState::Paused => {
-                if newly_pressed.contains(&Scancode::P) {
+                if frame.pause {
                     self.state = GameState::Playing;
                 }
             }
-            GameState::GameOver => {
-                if newly_pressed.contains(&Scancode::Space) {
-                    engine.clear_board();
-                    engine.place_cursor();
-                    self.state = GameState::Playing
+            GameState::GameOver | GameState::Finished => {
+                if newly_pressed.contains(&Scancode::Space) || start {
+                    self.replay = None;
+                    self.start_game(engine);
                 }
             }
         }
     }
 
-    fn draw_text(
-        &self,
-        msg: &str,
-        canvas: &mut WindowCanvas,
-        font: &mut Font,
-        color: Color,
-        x: u32,
-        y: u32,
-        centered: bool,
-    ) {
+    fn draw_text(&self, msg: &str, canvas: &mut WindowCanvas, font: &mut Font, style: TextStyle) {
+        let TextStyle {
+            color,
+            x,
+            y,
+            centered,
+        } = style;
         let texture_creator = canvas.texture_creator();
 
         // render a surface, and convert it to a texture bound to the canvas
@@ -514,59 +919,148 @@ impl Interface {
         offset: Option<u32>,
     ) {
         let y_offset = offset.unwrap_or(0);
-        self.draw_text(msg, canvas, font, Color::RED, 0, y_offset, true)
+        self.draw_text(msg, canvas, font, TextStyle::centered(Color::RED, y_offset))
+    }
+
+    fn draw_settings(
+        &self,
+        canvas: &mut WindowCanvas,
+        font: &mut Font,
+        selected: usize,
+        waiting: bool,
+    ) {
+        let spacing: u32 = font.height() as u32;
+        let mut lines: Vec<String> = KeyAction::ALL
+            .iter()
+            .map(|action| {
+                let keys: Vec<&str> = self
+                    .keyboard
+                    .bindings
+                    .keys(*action)
+                    .iter()
+                    .map(|key| key.name())
+                    .collect();
+                format!("{:<14}{}", action.name(), keys.join(", "))
+            })
+            .collect();
+        lines.push(format!("{:<14}{} ms", "das", self.handling.das.as_millis()));
+        lines.push(format!("{:<14}{} ms", "arr", self.handling.arr.as_millis()));
+        lines.push(format!("{:<14}{} ms", "dcd", self.handling.dcd.as_millis()));
+        lines.push(format!("{:<14}{}", "sdf", self.handling.soft_drop));
+        for (i, mut line) in lines.into_iter().enumerate() {
+            if i == selected && waiting {
+                line.push_str(" <press a key>");
+            }
+            let color = if i == selected {
+                Color::RED
+            } else {
+                Color::BLACK
+            };
+            self.draw_text(
+                &line,
+                canvas,
+                font,
+                TextStyle::at(color, 140, 110 + i as u32 * spacing),
+            );
+        }
+        self.draw_text(
+            "UP/DOWN select  RETURN add key  BACKSPACE clear",
+            canvas,
+            font,
+            TextStyle::centered(Color::BLACK, 200),
+        );
+        self.draw_text(
+            "LEFT/RIGHT change value  ESC save",
+            canvas,
+            font,
+            TextStyle::centered(Color::BLACK, 200 + spacing),
+        );
+    }
+
+    fn draw_mode_setup(&self, canvas: &mut WindowCanvas, font: &mut Font, selected: usize) {
+        let spacing: u32 = font.height() as u32;
+        for (i, (name, value)) in self.modes[self.mode].options().iter().enumerate() {
+            let color = if i == selected {
+                Color::RED
+            } else {
+                Color::BLACK
+            };
+            let line = format!("{:<14}{}", name, value);
+            self.draw_text(
+                &line,
+                canvas,
+                font,
+                TextStyle::at(color, 140, 110 + i as u32 * spacing),
+            );
+        }
+        self.draw_text(
+            "UP/DOWN select  LEFT/RIGHT change  SPACE start  ESC back",
+            canvas,
+            font,
+            TextStyle::centered(Color::BLACK, 200),
+        );
     }
 
     fn draw_stats(&self, canvas: &mut WindowCanvas, engine: &Engine, font: &mut Font) {
         let spacing: u32 = font.height() as u32;
-        self.draw_text(&"Level", canvas, font, Color::BLACK, 10, 20, false);
+        self.draw_text("Level", canvas, font, TextStyle::at(Color::BLACK, 10, 20));
         self.draw_text(
             format!("{}", engine.level).as_str(),
             canvas,
             font,
-            Color::RED,
-            10,
-            20 + spacing,
-            false,
+            TextStyle::at(Color::RED, 10, 20 + spacing),
         );
 
         self.draw_text(
-            &"Score",
+            "Score",
             canvas,
             font,
-            Color::BLACK,
-            10,
-            20 + (spacing * 3),
-            false,
+            TextStyle::at(Color::BLACK, 10, 20 + (spacing * 3)),
         );
         self.draw_text(
             format!("{}", engine.points).as_str(),
             canvas,
             font,
-            Color::RED,
-            10,
-            20 + (spacing * 4),
-            false,
+            TextStyle::at(Color::RED, 10, 20 + (spacing * 4)),
         );
 
         self.draw_text(
-            &"Cleared",
+            "Cleared",
             canvas,
             font,
-            Color::BLACK,
-            10,
-            20 + (spacing * 6),
-            false,
+            TextStyle::at(Color::BLACK, 10, 20 + (spacing * 6)),
         );
         self.draw_text(
             format!("{}", engine.rows_cleared).as_str(),
             canvas,
             font,
-            Color::RED,
-            10,
-            20 + (spacing * 7),
-            false,
+            TextStyle::at(Color::RED, 10, 20 + (spacing * 7)),
         );
+
+        let mut y = 20 + spacing * 9;
+        for (label, value) in self.modes[self.mode].status(engine) {
+            self.draw_text(&label, canvas, font, TextStyle::at(Color::BLACK, 10, y));
+            self.draw_text(
+                &value,
+                canvas,
+                font,
+                TextStyle::at(Color::RED, 10, y + spacing),
+            );
+            y += spacing * 3;
+        }
+    }
+
+    fn draw_headline(&self, canvas: &mut WindowCanvas, engine: &Engine, font: &mut Font) {
+        if let Some(headline) = self.modes[self.mode].headline(engine) {
+            self.draw_text(&headline, canvas, font, TextStyle::at(Color::RED, 460, 500));
+        }
+    }
+
+    fn draw_summary(&self, canvas: &mut WindowCanvas, engine: &Engine, font: &mut Font) {
+        let spacing: u32 = font.height() as u32;
+        for (i, line) in self.modes[self.mode].summary(engine).iter().enumerate() {
+            self.draw_title(line, canvas, font, Some(60 + i as u32 * spacing));
+        }
     }
 
     pub fn run(&mut self, engine: &mut Engine) {
@@ -592,43 +1086,67 @@ impl Interface {
         canvas.clear();
         canvas.present();
         let mut event_pump = sdl_context.event_pump().unwrap();
+        self.gamepad = sdl_context
+            .game_controller()
+            .ok()
+            .map(|subsystem| Gamepad::new(subsystem, self.handling));
+        if self.replay.is_some() {
+            self.start_game(engine);
+        }
         'running: loop {
             let loop_start = Instant::now();
             canvas.set_draw_color(Colors::BG);
             canvas.clear();
             for event in event_pump.poll_iter() {
+                if let Some(gamepad) = self.gamepad.as_mut() {
+                    gamepad.handle_event(&event);
+                }
                 match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
+                    Event::Quit { .. } => break 'running,
+                    Event::KeyDown {
                         keycode: Some(Keycode::Escape),
                         ..
                     }
                     | Event::KeyDown {
                         keycode: Some(Keycode::Q),
                         ..
-                    } => break 'running,
-                    Event::KeyDown {
-                        keycode: Some(Keycode::Period),
-                        ..
-                    } => queue.shown_items = cmp::min(7, queue.shown_items + 1),
-                    Event::KeyDown {
-                        keycode: Some(Keycode::Comma),
-                        ..
-                    } => queue.shown_items = cmp::max(1, queue.shown_items - 1),
-                    Event::KeyDown {
-                        keycode: Some(Keycode::Plus),
-                        ..
-                    } => engine.level = cmp::min(15, engine.level + 1),
+                    } if !matches!(
+                        self.state,
+                        GameState::Settings(..) | GameState::ModeSetup(_)
+                    ) =>
+                    {
+                        break 'running
+                    }
                     Event::KeyDown {
-                        keycode: Some(Keycode::Num0),
+                        scancode: Some(scancode),
                         ..
-                    } => engine.level = cmp::max(1, engine.level - 1),
+                    } if !matches!(
+                        self.state,
+                        GameState::Settings(..) | GameState::ModeSetup(_)
+                    ) =>
+                    {
+                        match self.keyboard.bindings.action(scancode) {
+                            Some(KeyAction::QueueLonger) => {
+                                queue.shown_items = cmp::min(7, queue.shown_items + 1)
+                            }
+                            Some(KeyAction::QueueShorter) => {
+                                queue.shown_items = cmp::max(1, queue.shown_items - 1)
+                            }
+                            Some(KeyAction::LevelUp) if self.level_keys() => {
+                                engine.level = cmp::min(15, engine.level + 1)
+                            }
+                            Some(KeyAction::LevelDown) if self.level_keys() => {
+                                engine.level = cmp::max(1, engine.level - 1)
+                            }
+                            _ => (),
+                        }
+                    }
                     _ => {}
                 }
             }
             self.handle_input(engine, &mut event_pump);
             match self.state {
-                GameState::TitleScreen => {
+                GameState::TitleScreen(_) => {
                     self.draw_title("Tetris", &mut canvas, &mut font_title, None);
                     self.draw_title(
                         ">PRESS SPACE TO START<",
@@ -636,28 +1154,60 @@ impl Interface {
                         &mut font_stats,
                         Some(60),
                     );
+                    self.draw_title("K: SETTINGS", &mut canvas, &mut font_stats, Some(90));
+                    let mode = format!("M: MODE {}", self.modes[self.mode].name().to_uppercase());
+                    self.draw_title(&mode, &mut canvas, &mut font_stats, Some(120));
+                }
+                GameState::Settings(selected, waiting) => {
+                    self.draw_text(
+                        "SETTINGS",
+                        &mut canvas,
+                        &mut font_title,
+                        TextStyle::at(Color::RED, 140, 40),
+                    );
+                    self.draw_settings(&mut canvas, &mut font_stats, selected, waiting);
+                }
+                GameState::ModeSetup(selected) => {
+                    self.draw_text(
+                        &self.modes[self.mode].name().to_uppercase(),
+                        &mut canvas,
+                        &mut font_title,
+                        TextStyle::at(Color::RED, 140, 40),
+                    );
+                    self.draw_mode_setup(&mut canvas, &mut font_stats, selected);
+                }
+                GameState::Demo => {
+                    matrix.draw(engine.level, &mut canvas, engine);
+                    self.draw_stats(&mut canvas, engine, &mut font_stats);
+                    queue.draw(engine.level, &mut canvas, engine);
+                    self.draw_title(">DEMO<", &mut canvas, &mut font_title, None)
                 }
                 GameState::Playing => {
-                    match engine.tick(self.soft_drop) {
-                        Err(e) => {
-                            println!("GAMEOVERTICK {:?}", e);
-                            self.state = GameState::GameOver
-                        }
-                        Ok(()) => (),
-                    }
-                    matrix.draw(engine.level, &mut canvas, &engine);
-                    self.draw_stats(&mut canvas, &engine, &mut font_stats);
-                    queue.draw(engine.level, &mut canvas, &engine);
+                    matrix.draw(engine.level, &mut canvas, engine);
+                    self.draw_stats(&mut canvas, engine, &mut font_stats);
+                    queue.draw(engine.level, &mut canvas, engine);
+                    self.draw_headline(&mut canvas, engine, &mut font_title);
                 }
                 GameState::Paused => {
-                    matrix.draw(engine.level, &mut canvas, &engine);
-                    self.draw_stats(&mut canvas, &engine, &mut font_stats);
-                    queue.draw(engine.level, &mut canvas, &engine);
+                    matrix.draw(engine.level, &mut canvas, engine);
+                    self.draw_stats(&mut canvas, engine, &mut font_stats);
+                    queue.draw(engine.level, &mut canvas, engine);
+                    self.draw_headline(&mut canvas, engine, &mut font_title);
                     self.draw_title(">PAUSE<", &mut canvas, &mut font_title, None)
                 }
                 GameState::GameOver => {
-                    self.draw_title("GAME OVER. :(", &mut canvas, &mut font_title, None)
+                    self.draw_title("GAME OVER. :(", &mut canvas, &mut font_title, None);
+                    self.draw_summary(&mut canvas, engine, &mut font_stats);
                 }
+                GameState::Finished => {
+                    self.draw_title("FINISHED!", &mut canvas, &mut font_title, None);
+                    self.draw_summary(&mut canvas, engine, &mut font_stats);
+                }
+            }
+
+            let state = self.state_name();
+            if let Some(control) = self.control.as_mut() {
+                control.broadcast(&Snapshot::new(state, engine));
             }
 
             canvas.present();
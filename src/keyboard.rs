@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use sdl2::keyboard::Scancode;
+use sdl2::EventPump;
+use tetris::engine::Engine;
+use tetris::input::{Action, Buttons, Frame, Handling, InputSource};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Game(Action),
+    QueueLonger,
+    QueueShorter,
+    LevelUp,
+    LevelDown,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 12] = [
+        KeyAction::Game(Action::Left),
+        KeyAction::Game(Action::Right),
+        KeyAction::Game(Action::RotateCw),
+        KeyAction::Game(Action::RotateCcw),
+        KeyAction::Game(Action::Rotate180),
+        KeyAction::Game(Action::SoftDrop),
+        KeyAction::Game(Action::HardDrop),
+        KeyAction::Game(Action::Pause),
+        KeyAction::QueueLonger,
+        KeyAction::QueueShorter,
+        KeyAction::LevelUp,
+        KeyAction::LevelDown,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyAction::Game(action) => action.name(),
+            KeyAction::QueueLonger => "queue_longer",
+            KeyAction::QueueShorter => "queue_shorter",
+            KeyAction::LevelUp => "level_up",
+            KeyAction::LevelDown => "level_down",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyBindings(HashMap<KeyAction, Vec<Scancode>>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        for (action, keys) in [
+            (KeyAction::Game(Action::Left), vec![Scancode::Left]),
+            (KeyAction::Game(Action::Right), vec![Scancode::Right]),
+            (KeyAction::Game(Action::RotateCw), vec![Scancode::RCtrl]),
+            (KeyAction::Game(Action::RotateCcw), vec![Scancode::Up]),
+            (KeyAction::Game(Action::Rotate180), vec![Scancode::RShift]),
+            (KeyAction::Game(Action::SoftDrop), vec![Scancode::Down]),
+            (KeyAction::Game(Action::HardDrop), vec![Scancode::Space]),
+            (KeyAction::Game(Action::Pause), vec![Scancode::P]),
+            (KeyAction::QueueLonger, vec![Scancode::Period]),
+            (KeyAction::QueueShorter, vec![Scancode::Comma]),
+            (KeyAction::LevelUp, vec![Scancode::Equals, Scancode::KpPlus]),
+            (KeyAction::LevelDown, vec![Scancode::Num0]),
+        ] {
+            bindings.insert(action, keys);
+        }
+        KeyBindings(bindings)
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: KeyAction) -> &[Scancode] {
+        self.0.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn action(&self, scancode: Scancode) -> Option<KeyAction> {
+        KeyAction::ALL
+            .into_iter()
+            .find(|action| self.keys(*action).contains(&scancode))
+    }
+
+    // A key drives a single action, so binding it takes it off any other.
+    pub fn bind(&mut self, action: KeyAction, scancode: Scancode) {
+        for keys in self.0.values_mut() {
+            keys.retain(|key| *key != scancode);
+        }
+        self.0.entry(action).or_default().push(scancode);
+    }
+
+    pub fn clear(&mut self, action: KeyAction) {
+        self.0.insert(action, vec![]);
+    }
+
+    // One "action = key" per line, SDL key names, an action taking every
+    // key given for it; actions not in the file keep their defaults.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut bindings = KeyBindings::default();
+        let mut seen = vec![];
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, key) = line
+                .split_once('=')
+                .ok_or(format!("{}: expected action = key", line))?;
+            let action = KeyAction::ALL
+                .into_iter()
+                .find(|action| action.name() == name.trim())
+                .ok_or(format!("unknown action {:?}", name.trim()))?;
+            if !seen.contains(&action) {
+                seen.push(action);
+                bindings.clear(action);
+            }
+            // An action listed without a key is deliberately left unbound.
+            if !key.trim().is_empty() {
+                let scancode = Scancode::from_name(key.trim())
+                    .ok_or(format!("unknown key {:?}", key.trim()))?;
+                bindings.bind(action, scancode);
+            }
+        }
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut text = String::new();
+        for action in KeyAction::ALL {
+            if self.keys(action).is_empty() {
+                text.push_str(&format!("{} =\n", action.name()));
+            }
+            for key in self.keys(action) {
+                text.push_str(&format!("{} = {}\n", action.name(), key.name()));
+            }
+        }
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+pub struct Keyboard {
+    pub bindings: KeyBindings,
+    buttons: Buttons,
+}
+
+impl Keyboard {
+    pub fn new(bindings: KeyBindings, handling: Handling) -> Self {
+        Keyboard {
+            bindings,
+            buttons: Buttons::new(handling),
+        }
+    }
+
+    pub fn set_handling(&mut self, handling: Handling) {
+        self.buttons.handling = handling;
+    }
+
+    // Reads the keys held down, for the next frame.
+    pub fn update(&mut self, event_pump: &EventPump) {
+        let down: Vec<Action> = event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(|scancode| match self.bindings.action(scancode) {
+                Some(KeyAction::Game(action)) => Some(action),
+                _ => None,
+            })
+            .collect();
+        self.buttons.set_down(down);
+    }
+}
+
+impl InputSource for Keyboard {
+    fn frame(&mut self, engine: &Engine) -> Frame {
+        self.buttons.frame(engine)
+    }
+}
@@ -0,0 +1,7 @@
+pub mod ai;
+pub mod engine;
+pub mod env;
+pub mod input;
+pub mod mode;
+pub mod sim;
+pub mod tbp;
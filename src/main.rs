@@ -1,8 +1,100 @@
-mod engine;
+mod control;
+mod gamepad;
 mod interface;
+mod keyboard;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tetris::engine;
+use tetris::input::Replay;
 
 fn main() {
     let mut engine = engine::Engine::new();
     let mut if_ = interface::Interface::new();
+    if let Err(e) = if_.load_weights() {
+        eprintln!("Cannot load AI weights: {}", e);
+        std::process::exit(1);
+    }
+    let mut replay = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bot", Some(command)) => if_.use_bot(command),
+            ("--listen", Some(address)) => {
+                if let Err(e) = if_.listen(&address) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            ("--timing", Some(timing)) => match timing.parse() {
+                Ok(timing) => if_.set_timing(timing),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            },
+            ("--clear", Some(style)) => match style.parse() {
+                Ok(style) => if_.set_clear_style(style),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            },
+            ("--gravity", Some(gravity)) => match gravity.parse() {
+                Ok(gravity) => if_.set_clear_gravity(gravity),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            },
+            ("--mode", Some(mode)) => {
+                if let Err(e) = if_.select_mode(&mode) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            ("--sprint-lines", Some(lines)) => match lines.parse() {
+                Ok(lines) => if_.set_sprint_lines(lines),
+                Err(_) => {
+                    eprintln!("not a number of lines: {:?}", lines);
+                    std::process::exit(1);
+                }
+            },
+            ("--ultra-seconds", Some(seconds)) => match seconds.parse() {
+                Ok(seconds) => if_.set_ultra_time(Duration::from_secs(seconds)),
+                Err(_) => {
+                    eprintln!("not a number of seconds: {:?}", seconds);
+                    std::process::exit(1);
+                }
+            },
+            ("--record", Some(path)) => if_.record(PathBuf::from(path)),
+            ("--replay", Some(path)) => match Replay::load(Path::new(&path)) {
+                Ok(loaded) => replay = Some(loaded),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            },
+            _ => {
+                eprintln!(
+                    "usage: tetris [--bot COMMAND] [--listen HOST:PORT | --listen unix:PATH] \
+                     [--timing modern|classic|instant|ARE/CLEAR/LOCK] \
+                     [--clear simultaneous|sequential] [--gravity naive|sticky|cascade] \
+                     [--mode endless|marathon|sprint|ultra|dig|survival|master] [--sprint-lines N] \
+                     [--ultra-seconds N] [--record FILE | --replay FILE]"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    // After the other arguments, so the replay's own mode wins.
+    if let Some(replay) = replay {
+        if let Err(e) = if_.replay(replay) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
     if_.run(&mut engine);
 }
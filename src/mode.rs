@@ -0,0 +1,232 @@
+pub mod dig;
+pub mod marathon;
+pub mod master;
+pub mod sprint;
+pub mod survival;
+pub mod ultra;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::engine::{Board, Engine};
+use crate::sim::FRAME;
+
+pub use self::dig::Dig;
+pub use self::marathon::Marathon;
+pub use self::master::Master;
+pub use self::sprint::Sprint;
+pub use self::survival::Survival;
+pub use self::ultra::Ultra;
+
+// The rules a game is played by on top of the engine: how it is set up,
+// when it ends other than by topping out, and what is shown for it.
+pub trait Mode {
+    fn name(&self) -> &'static str;
+    // Readies the mode and a cleared engine for a new game.
+    fn start(&mut self, engine: &mut Engine);
+    // Called after every frame played, true once the game's goal is
+    // reached. Errors if the mode topped the player out.
+    fn update(&mut self, engine: &mut Engine) -> Result<bool, String>;
+    // Labels and values shown beside the board.
+    fn status(&self, engine: &Engine) -> Vec<(String, String)>;
+    // Lines for the screen after the game.
+    fn summary(&self, engine: &Engine) -> Vec<String>;
+    // Shown large while playing, for what the player has to keep an eye on.
+    fn headline(&self, _engine: &Engine) -> Option<String> {
+        None
+    }
+    // Names and values of what the player picks before a game, if any.
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+    // Steps an option's value up or down.
+    fn adjust(&mut self, _option: usize, _up: bool) {}
+    // Everything a game of the mode depends on besides the engine, as
+    // names and values a replay can set it up again from.
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+    // Sets one of the values settings() gives.
+    fn set(&mut self, name: &str, _value: &str) -> Result<(), String> {
+        Err(unknown_setting(self.name(), name))
+    }
+}
+
+fn unknown_setting(mode: &str, name: &str) -> String {
+    format!("{} has no setting {:?}", mode, name)
+}
+
+fn parse_setting<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} cannot be {:?}", name, value))
+}
+
+// Plays on until topping out.
+pub struct Endless;
+
+impl Mode for Endless {
+    fn name(&self) -> &'static str {
+        "endless"
+    }
+
+    fn start(&mut self, _engine: &mut Engine) {}
+
+    fn update(&mut self, _engine: &mut Engine) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn status(&self, _engine: &Engine) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    fn summary(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("SCORE {}", engine.points),
+            format!("LINES {}", engine.rows_cleared),
+        ]
+    }
+}
+
+// Game time counted in frames played, so pauses and slow frames don't
+// count and a replay times the same as the game it recorded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timer {
+    frames: u32,
+}
+
+impl Timer {
+    pub fn tick(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        FRAME * self.frames
+    }
+}
+
+// As minutes, seconds and milliseconds, like 1:02.345.
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+// How far ahead (-) or behind (+) a personal best a time is.
+pub fn format_delta(time: Duration, best: Duration) -> String {
+    match time.checked_sub(best) {
+        Some(behind) => format!("+{}.{:03}", behind.as_secs(), behind.subsec_millis()),
+        None => {
+            let ahead = best - time;
+            format!("-{}.{:03}", ahead.as_secs(), ahead.subsec_millis())
+        }
+    }
+}
+
+// Hole columns for garbage rows, in the order the rows go in: each keeps
+// the hole of the one before unless messiness, from 0 to 1, moves it.
+pub struct Garbage {
+    rng: ChaCha8Rng,
+    hole: Option<isize>,
+    pub messiness: f64,
+}
+
+impl Garbage {
+    pub fn new(seed: u64, messiness: f64) -> Self {
+        Garbage {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            hole: None,
+            messiness: messiness.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn holes(&mut self, rows: usize) -> Vec<isize> {
+        (0..rows).map(|_| self.next_hole()).collect()
+    }
+
+    fn next_hole(&mut self) -> isize {
+        let hole = match self.hole {
+            Some(hole) if !self.rng.gen_bool(self.messiness) => hole,
+            // Anywhere but where it was.
+            Some(hole) => (hole + self.rng.gen_range(1..Board::WIDTH)) % Board::WIDTH,
+            None => self.rng.gen_range(0..Board::WIDTH),
+        };
+        self.hole = Some(hole);
+        hole
+    }
+}
+
+// Personal bests, one "name = value value ..." per line.
+#[derive(Clone, Debug, Default)]
+pub struct Records(BTreeMap<String, Vec<u64>>);
+
+impl Records {
+    // No file yet means no records.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut records = Records::default();
+        if !path.exists() {
+            return Ok(records);
+        }
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, values) = line
+                .split_once('=')
+                .ok_or(format!("{}: expected name = values", line))?;
+            let values = values
+                .split_whitespace()
+                .map(|value| value.parse())
+                .collect::<Result<Vec<u64>, _>>()
+                .map_err(|_| format!("{}: not a list of numbers", line))?;
+            records.0.insert(name.trim().to_string(), values);
+        }
+        Ok(records)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut text = String::new();
+        for (name, values) in &self.0 {
+            let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            text.push_str(&format!("{} = {}\n", name, values.join(" ")));
+        }
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[u64]> {
+        self.0.get(name).map(|values| values.as_slice())
+    }
+
+    pub fn set(&mut self, name: &str, values: Vec<u64>) {
+        self.0.insert(name.to_string(), values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn garbage_holes_follow_messiness() {
+        let clean = Garbage::new(1, 0.0).holes(20);
+        assert!(clean.iter().all(|hole| *hole == clean[0]));
+        let messy = Garbage::new(1, 1.0).holes(20);
+        assert!(messy.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!(messy.iter().all(|hole| (0..Board::WIDTH).contains(hole)));
+        assert_eq!(
+            Garbage::new(7, 0.5).holes(20),
+            Garbage::new(7, 0.5).holes(20)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use super::{format_time, parse_setting, unknown_setting, Garbage, Mode, Timer};
+use crate::engine::Engine;
+
+// Dig through rows of garbage as fast as possible.
+pub struct Dig {
+    pub rows: usize,
+    // Chance out of 10 of a row's hole moving from where the last one's was.
+    pub messiness: usize,
+    timer: Timer,
+}
+
+impl Default for Dig {
+    fn default() -> Self {
+        Dig {
+            rows: 10,
+            messiness: 3,
+            timer: Timer::default(),
+        }
+    }
+}
+
+impl Dig {
+    // Leaving room above the garbage to play in.
+    const MAX_ROWS: usize = 16;
+}
+
+impl Mode for Dig {
+    fn name(&self) -> &'static str {
+        "dig"
+    }
+
+    fn start(&mut self, engine: &mut Engine) {
+        self.timer = Timer::default();
+        let mut garbage = Garbage::new(engine.random_seed(), self.messiness as f64 / 10.0);
+        // Nothing is on a cleared board to be pushed out of the top.
+        let _ = engine.add_garbage(&garbage.holes(self.rows));
+    }
+
+    fn update(&mut self, engine: &mut Engine) -> Result<bool, String> {
+        self.timer.tick();
+        Ok(engine.board().garbage_rows() == 0)
+    }
+
+    fn status(&self, engine: &Engine) -> Vec<(String, String)> {
+        vec![
+            ("Time".to_string(), format_time(self.timer.elapsed())),
+            (
+                "Garbage".to_string(),
+                format!("{}/{}", engine.board().garbage_rows(), self.rows),
+            ),
+        ]
+    }
+
+    fn summary(&self, engine: &Engine) -> Vec<String> {
+        let left = engine.board().garbage_rows();
+        let mut summary = match left {
+            0 => vec![format!("TIME {}", format_time(self.timer.elapsed()))],
+            _ => vec![format!("{} OF {} ROWS LEFT", left, self.rows)],
+        };
+        summary.push(format!("PIECES {}", engine.pieces));
+        summary
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("garbage rows", self.rows.to_string()),
+            ("messiness", format!("{}%", self.messiness * 10)),
+        ]
+    }
+
+    fn adjust(&mut self, option: usize, up: bool) {
+        match (option, up) {
+            (0, true) => self.rows = (self.rows + 1).min(Dig::MAX_ROWS),
+            (0, false) => self.rows = (self.rows - 1).max(1),
+            (1, true) => self.messiness = (self.messiness + 1).min(10),
+            (1, false) => self.messiness = self.messiness.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("rows", self.rows.to_string()),
+            ("messiness", self.messiness.to_string()),
+        ]
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "rows" => self.rows = parse_setting::<usize>(name, value)?.clamp(1, Dig::MAX_ROWS),
+            "messiness" => self.messiness = parse_setting::<usize>(name, value)?.min(10),
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use crate::engine::{Engine, Progression};
+
+use super::{parse_setting, unknown_setting, Mode};
+
+// Play from a chosen level until a number of lines, or on for ever.
+pub struct Marathon {
+    pub start_level: usize,
+    pub progression: Progression,
+    pub goal: Option<usize>,
+}
+
+impl Default for Marathon {
+    fn default() -> Self {
+        Marathon {
+            start_level: 1,
+            progression: Progression::default(),
+            goal: Some(150),
+        }
+    }
+}
+
+impl Marathon {
+    const MAX_LEVEL: usize = 15;
+    const GOALS: [Option<usize>; 3] = [Some(150), Some(200), None];
+}
+
+impl Mode for Marathon {
+    fn name(&self) -> &'static str {
+        "marathon"
+    }
+
+    fn start(&mut self, engine: &mut Engine) {
+        engine.progression = self.progression;
+        engine.set_start_level(self.start_level);
+    }
+
+    fn update(&mut self, engine: &mut Engine) -> Result<bool, String> {
+        Ok(self.goal.is_some_and(|goal| engine.rows_cleared >= goal))
+    }
+
+    fn status(&self, engine: &Engine) -> Vec<(String, String)> {
+        let lines = match self.goal {
+            Some(goal) => format!("{}/{}", engine.rows_cleared.min(goal), goal),
+            None => engine.rows_cleared.to_string(),
+        };
+        vec![("Lines".to_string(), lines)]
+    }
+
+    fn summary(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("SCORE {}", engine.points),
+            format!("LINES {}", engine.rows_cleared),
+            format!("LEVEL {} TO {}", self.start_level, engine.level),
+        ]
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        let goal = match self.goal {
+            Some(goal) => format!("{} lines", goal),
+            None => "endless".to_string(),
+        };
+        vec![
+            ("start level", self.start_level.to_string()),
+            ("progression", self.progression.to_string()),
+            ("finish", goal),
+        ]
+    }
+
+    fn adjust(&mut self, option: usize, up: bool) {
+        match option {
+            0 => {
+                self.start_level = match up {
+                    true => (self.start_level + 1).min(Marathon::MAX_LEVEL),
+                    false => (self.start_level - 1).max(1),
+                }
+            }
+            1 => {
+                self.progression = match self.progression {
+                    Progression::Classic => Progression::Guideline,
+                    Progression::Guideline => Progression::Classic,
+                }
+            }
+            2 => {
+                let goals = Marathon::GOALS.len();
+                let i = Marathon::GOALS
+                    .iter()
+                    .position(|goal| *goal == self.goal)
+                    .unwrap_or(0);
+                let i = match up {
+                    true => (i + 1) % goals,
+                    false => (i + goals - 1) % goals,
+                };
+                self.goal = Marathon::GOALS[i];
+            }
+            _ => (),
+        }
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        let goal = match self.goal {
+            Some(goal) => goal.to_string(),
+            None => "none".to_string(),
+        };
+        vec![
+            ("level", self.start_level.to_string()),
+            ("progression", self.progression.to_string()),
+            ("goal", goal),
+        ]
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match (name, value) {
+            ("level", _) => {
+                self.start_level =
+                    parse_setting::<usize>(name, value)?.clamp(1, Marathon::MAX_LEVEL)
+            }
+            ("progression", _) => self.progression = value.parse()?,
+            ("goal", "none") => self.goal = None,
+            ("goal", _) => self.goal = Some(parse_setting(name, value)?),
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use super::{format_time, Mode, Timer};
+use crate::engine::{Engine, Gravity, Timing};
+
+// Race to level 999 on a counter that goes up by one a piece and by the
+// lines cleared, speeding up to 20G, then the delays shortening section
+// by section. Each section is 100 levels; the last level of one only
+// passes with a line clear.
+#[derive(Default)]
+pub struct Master {
+    level: usize,
+    pieces: usize,
+    lines: usize,
+    timer: Timer,
+    sections: Vec<Duration>,
+}
+
+impl Master {
+    const MAX_LEVEL: usize = 999;
+    const SECTION: usize = 100;
+    const TWENTY_G: usize = 500;
+    // A section done faster than this counts toward the grade.
+    const SECTION_PAR: Duration = Duration::from_secs(75);
+    const GRAND_MASTER_TIME: Duration = Duration::from_secs(13 * 60 + 30);
+    const GRADES: [&'static str; 19] = [
+        "9", "8", "7", "6", "5", "4", "3", "2", "1", "S1", "S2", "S3", "S4", "S5", "S6", "S7",
+        "S8", "S9", "GM",
+    ];
+    // Entry, line clear and lock delays in ms from section 5 on; earlier
+    // sections use the first.
+    const DELAYS: [(u64, u64, u64); 5] = [
+        (417, 417, 500),
+        (333, 267, 500),
+        (300, 200, 467),
+        (267, 100, 367),
+        (233, 100, 283),
+    ];
+
+    fn section(&self) -> usize {
+        self.level / Master::SECTION
+    }
+
+    // The level the counter stops at until a line is cleared.
+    fn stop(&self) -> usize {
+        ((self.section() + 1) * Master::SECTION - 1).min(Master::MAX_LEVEL - 1)
+    }
+
+    fn timing(&self) -> Timing {
+        let section = self
+            .section()
+            .saturating_sub(Master::TWENTY_G / Master::SECTION);
+        let (entry, clear, lock) = match self.section() * Master::SECTION < Master::TWENTY_G {
+            true => (417, 667, 500),
+            false => Master::DELAYS[section.min(Master::DELAYS.len() - 1)],
+        };
+        Timing {
+            entry_delay: Duration::from_millis(entry),
+            line_clear_delay: Duration::from_millis(clear),
+            lock_delay: Duration::from_millis(lock),
+        }
+    }
+
+    fn set_speed(&self, engine: &mut Engine) {
+        if self.level >= Master::TWENTY_G {
+            engine.gravity = Gravity::TwentyG;
+        } else {
+            engine.gravity = Gravity::Level;
+            // Up the engine's 15 speeds on the way to 20G.
+            engine.level = 1 + self.level * 15 / Master::TWENTY_G;
+        }
+        engine.timing = self.timing();
+    }
+
+    // One grade per 60 levels reached and one more for every two sections
+    // under par; the grand master has to reach 999 in time.
+    fn grade(&self) -> &'static str {
+        let elapsed = self.timer.elapsed();
+        if self.level >= Master::MAX_LEVEL && elapsed <= Master::GRAND_MASTER_TIME {
+            return Master::GRADES[Master::GRADES.len() - 1];
+        }
+        let fast = self
+            .sections
+            .iter()
+            .filter(|time| **time < Master::SECTION_PAR)
+            .count();
+        let grade = (self.level / 60).min(16) + fast / 2;
+        Master::GRADES[grade.min(Master::GRADES.len() - 2)]
+    }
+
+    fn section_time(&self) -> Duration {
+        self.timer.elapsed() - self.sections.iter().sum::<Duration>()
+    }
+}
+
+impl Mode for Master {
+    fn name(&self) -> &'static str {
+        "master"
+    }
+
+    fn start(&mut self, engine: &mut Engine) {
+        *self = Master::default();
+        self.set_speed(engine);
+    }
+
+    fn update(&mut self, engine: &mut Engine) -> Result<bool, String> {
+        self.timer.tick();
+        for _ in self.pieces..engine.pieces {
+            if self.level < self.stop() {
+                self.level += 1;
+            }
+        }
+        self.level = (self.level + engine.rows_cleared - self.lines).min(Master::MAX_LEVEL);
+        self.pieces = engine.pieces;
+        self.lines = engine.rows_cleared;
+        while self.sections.len() < self.section() {
+            self.sections.push(self.section_time());
+        }
+        if self.level >= Master::MAX_LEVEL {
+            self.sections.push(self.section_time());
+            return Ok(true);
+        }
+        self.set_speed(engine);
+        Ok(false)
+    }
+
+    fn status(&self, _engine: &Engine) -> Vec<(String, String)> {
+        vec![
+            (
+                "Level".to_string(),
+                format!("{}/{}", self.level, self.stop() + 1),
+            ),
+            ("Time".to_string(), format_time(self.timer.elapsed())),
+            ("Section".to_string(), format_time(self.section_time())),
+            ("Grade".to_string(), self.grade().to_string()),
+        ]
+    }
+
+    fn summary(&self, _engine: &Engine) -> Vec<String> {
+        let mut summary = vec![
+            format!("GRADE {}", self.grade()),
+            format!("LEVEL {}", self.level),
+            format!("TIME {}", format_time(self.timer.elapsed())),
+        ];
+        for (i, time) in self.sections.iter().enumerate() {
+            summary.push(format!("{:>3} {}", i * Master::SECTION, format_time(*time)));
+        }
+        summary
+    }
+
+    fn headline(&self, _engine: &Engine) -> Option<String> {
+        Some(self.level.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_counts_pieces_and_lines_and_stops_at_sections() {
+        let mut engine = Engine::simulated(0);
+        let mut master = Master::default();
+        master.start(&mut engine);
+        engine.pieces = 150;
+        assert_eq!(master.update(&mut engine), Ok(false));
+        assert_eq!(master.level, 99);
+        engine.rows_cleared = 2;
+        master.update(&mut engine).unwrap();
+        assert_eq!(master.level, 101);
+        assert_eq!(master.sections.len(), 1);
+        engine.pieces += 1;
+        master.update(&mut engine).unwrap();
+        assert_eq!(master.level, 102);
+    }
+
+    #[test]
+    fn twenty_g_and_shorter_delays_from_level_500() {
+        let mut engine = Engine::simulated(0);
+        let mut master = Master::default();
+        master.start(&mut engine);
+        assert_eq!(engine.gravity, Gravity::Level);
+        assert_eq!(engine.level, 1);
+        engine.rows_cleared = 499;
+        master.update(&mut engine).unwrap();
+        assert_eq!(engine.gravity, Gravity::Level);
+        assert_eq!(engine.level, 15);
+        engine.rows_cleared = 500;
+        master.update(&mut engine).unwrap();
+        assert_eq!(engine.gravity, Gravity::TwentyG);
+        assert_eq!(engine.timing.lock_delay, Duration::from_millis(500));
+        engine.rows_cleared = 900;
+        master.update(&mut engine).unwrap();
+        assert_eq!(engine.timing.lock_delay, Duration::from_millis(283));
+        engine.rows_cleared = 999;
+        assert_eq!(master.update(&mut engine), Ok(true));
+        assert_eq!(master.sections.len(), 10);
+    }
+
+    #[test]
+    fn grades() {
+        let slow = Timer {
+            frames: 60 * 60 * 15,
+        };
+        let grade = |level, sections: Vec<Duration>, timer| {
+            Master {
+                level,
+                sections,
+                timer,
+                ..Master::default()
+            }
+            .grade()
+        };
+        assert_eq!(grade(0, vec![], slow), "9");
+        assert_eq!(grade(130, vec![], slow), "7");
+        let fast = vec![Duration::from_secs(60); 4];
+        assert_eq!(grade(130, fast.clone(), slow), "5");
+        assert_eq!(grade(999, vec![], slow), "S8");
+        assert_eq!(grade(999, vec![Duration::from_secs(60); 10], slow), "S9");
+        assert_eq!(grade(999, fast, Timer::default()), "GM");
+    }
+}
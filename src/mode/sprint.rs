@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{format_delta, format_time, parse_setting, unknown_setting, Mode, Records, Timer};
+use crate::engine::Engine;
+
+// Clear a number of lines as fast as possible, with a split every ten
+// lines held against the personal best.
+pub struct Sprint {
+    pub lines: usize,
+    records: PathBuf,
+    timer: Timer,
+    splits: Vec<Duration>,
+    best: Option<Vec<Duration>>,
+    new_best: bool,
+}
+
+impl Sprint {
+    const SPLIT: usize = 10;
+
+    pub fn new(lines: usize, records: PathBuf) -> Self {
+        Sprint {
+            lines: lines.max(1),
+            records,
+            timer: Timer::default(),
+            splits: vec![],
+            best: None,
+            new_best: false,
+        }
+    }
+
+    fn record_name(&self) -> String {
+        format!("sprint{}", self.lines)
+    }
+
+    // Splits taken, the last of them at the finish.
+    fn split_count(&self) -> usize {
+        self.lines.div_ceil(Sprint::SPLIT)
+    }
+
+    fn finished(&self) -> bool {
+        self.splits.len() == self.split_count()
+    }
+
+    fn save_best(&mut self) {
+        if self
+            .best
+            .as_ref()
+            .and_then(|best| best.last())
+            .is_some_and(|best| self.splits.last().is_some_and(|time| time >= best))
+        {
+            return;
+        }
+        self.new_best = true;
+        let path = self.records.as_path();
+        let mut records = match Records::load(path) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("Cannot save personal best: {}", e);
+                return;
+            }
+        };
+        let times = self.splits.iter().map(|t| t.as_millis() as u64).collect();
+        records.set(&self.record_name(), times);
+        if let Err(e) = records.save(path) {
+            eprintln!("Cannot save personal best: {}", e);
+        }
+    }
+}
+
+impl Mode for Sprint {
+    fn name(&self) -> &'static str {
+        "sprint"
+    }
+
+    fn start(&mut self, _engine: &mut Engine) {
+        self.timer = Timer::default();
+        self.splits.clear();
+        self.new_best = false;
+        self.best = Records::load(self.records.as_path())
+            .map_err(|e| eprintln!("Cannot load personal bests: {}", e))
+            .ok()
+            .and_then(|records| {
+                records
+                    .get(&self.record_name())
+                    .map(|times| times.iter().map(|ms| Duration::from_millis(*ms)).collect())
+            })
+            .filter(|times: &Vec<Duration>| times.len() == self.split_count());
+    }
+
+    fn update(&mut self, engine: &mut Engine) -> Result<bool, String> {
+        self.timer.tick();
+        while !self.finished()
+            && engine.rows_cleared >= ((self.splits.len() + 1) * Sprint::SPLIT).min(self.lines)
+        {
+            self.splits.push(self.timer.elapsed());
+            if self.finished() {
+                self.save_best();
+            }
+        }
+        Ok(self.finished())
+    }
+
+    fn status(&self, engine: &Engine) -> Vec<(String, String)> {
+        let mut status = vec![
+            ("Time".to_string(), format_time(self.timer.elapsed())),
+            (
+                "Lines".to_string(),
+                format!("{}/{}", engine.rows_cleared.min(self.lines), self.lines),
+            ),
+        ];
+        if let Some(split) = self.splits.last() {
+            let mut value = format_time(*split);
+            if let Some(best) = &self.best {
+                value.push(' ');
+                value.push_str(&format_delta(*split, best[self.splits.len() - 1]));
+            }
+            status.push(("Split".to_string(), value));
+        }
+        if let Some(best) = self.best.as_ref().and_then(|best| best.last()) {
+            status.push(("Best".to_string(), format_time(*best)));
+        }
+        status
+    }
+
+    fn summary(&self, _engine: &Engine) -> Vec<String> {
+        let Some(time) = self.splits.last().filter(|_| self.finished()) else {
+            return vec![format!("{} LINES NOT CLEARED", self.lines)];
+        };
+        let mut summary = vec![format!("TIME {}", format_time(*time))];
+        for (i, split) in self.splits.iter().enumerate() {
+            let lines = ((i + 1) * Sprint::SPLIT).min(self.lines);
+            let mut line = format!("{:>3} {}", lines, format_time(*split));
+            if let Some(best) = &self.best {
+                line.push(' ');
+                line.push_str(&format_delta(*split, best[i]));
+            }
+            summary.push(line);
+        }
+        if self.new_best {
+            summary.push("NEW PERSONAL BEST".to_string());
+        }
+        summary
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![("lines", self.lines.to_string())]
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "lines" => self.lines = parse_setting::<usize>(name, value)?.max(1),
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+}
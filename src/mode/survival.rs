@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use super::{format_time, parse_setting, unknown_setting, Garbage, Mode, Timer};
+use crate::engine::Engine;
+
+// Hold out against garbage rising from below, faster and faster.
+pub struct Survival {
+    // Seconds before the first row rises.
+    pub interval: u64,
+    // Chance out of 10 of a row's hole moving from where the last one's was.
+    pub messiness: usize,
+    timer: Timer,
+    garbage: Garbage,
+    next_rise: Duration,
+    risen: usize,
+}
+
+impl Default for Survival {
+    fn default() -> Self {
+        Survival {
+            interval: 8,
+            messiness: 2,
+            timer: Timer::default(),
+            garbage: Garbage::new(0, 0.0),
+            next_rise: Duration::ZERO,
+            risen: 0,
+        }
+    }
+}
+
+impl Survival {
+    // Each row rises this much sooner after the last than the one before.
+    const SPEED_UP: f64 = 0.95;
+    const MIN_INTERVAL: Duration = Duration::from_millis(750);
+
+    fn interval(&self) -> Duration {
+        let interval =
+            Duration::from_secs(self.interval).mul_f64(Survival::SPEED_UP.powi(self.risen as i32));
+        interval.max(Survival::MIN_INTERVAL)
+    }
+}
+
+impl Mode for Survival {
+    fn name(&self) -> &'static str {
+        "survival"
+    }
+
+    fn start(&mut self, engine: &mut Engine) {
+        self.timer = Timer::default();
+        self.garbage = Garbage::new(engine.random_seed(), self.messiness as f64 / 10.0);
+        self.risen = 0;
+        self.next_rise = self.interval();
+    }
+
+    fn update(&mut self, engine: &mut Engine) -> Result<bool, String> {
+        self.timer.tick();
+        if self.timer.elapsed() >= self.next_rise {
+            self.risen += 1;
+            self.next_rise += self.interval();
+            engine.add_garbage(&self.garbage.holes(1))?;
+        }
+        Ok(false)
+    }
+
+    fn status(&self, _engine: &Engine) -> Vec<(String, String)> {
+        let until = self.next_rise.saturating_sub(self.timer.elapsed());
+        vec![
+            ("Survived".to_string(), format_time(self.timer.elapsed())),
+            (
+                "Next row".to_string(),
+                format!("{:.1}s", until.as_secs_f64()),
+            ),
+            ("Risen".to_string(), self.risen.to_string()),
+        ]
+    }
+
+    fn summary(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("SURVIVED {}", format_time(self.timer.elapsed())),
+            format!("ROWS RISEN {}", self.risen),
+            format!("LINES {}", engine.rows_cleared),
+        ]
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("first row", format!("{} s", self.interval)),
+            ("messiness", format!("{}%", self.messiness * 10)),
+        ]
+    }
+
+    fn adjust(&mut self, option: usize, up: bool) {
+        match (option, up) {
+            (0, true) => self.interval = (self.interval + 1).min(30),
+            (0, false) => self.interval = (self.interval - 1).max(1),
+            (1, true) => self.messiness = (self.messiness + 1).min(10),
+            (1, false) => self.messiness = self.messiness.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("interval", self.interval.to_string()),
+            ("messiness", self.messiness.to_string()),
+        ]
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "interval" => self.interval = parse_setting::<u64>(name, value)?.clamp(1, 30),
+            "messiness" => self.messiness = parse_setting::<usize>(name, value)?.min(10),
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::FRAME;
+
+    #[test]
+    fn rows_rise_sooner_and_sooner() {
+        let mut survival = Survival {
+            interval: 2,
+            ..Survival::default()
+        };
+        assert_eq!(survival.interval(), Duration::from_secs(2));
+        survival.risen = 1;
+        assert_eq!(survival.interval(), Duration::from_millis(1900));
+        survival.risen = 100;
+        assert_eq!(survival.interval(), Survival::MIN_INTERVAL);
+    }
+
+    #[test]
+    fn garbage_rises_on_time() {
+        let mut engine = Engine::simulated(3);
+        let mut survival = Survival {
+            interval: 1,
+            ..Survival::default()
+        };
+        survival.start(&mut engine);
+        while survival.risen < 2 {
+            assert_eq!(survival.update(&mut engine), Ok(false));
+        }
+        // One second, then 0.95 of one.
+        assert!(survival.timer.elapsed() >= Duration::from_millis(1950));
+        assert!(survival.timer.elapsed() < Duration::from_millis(1950) + FRAME);
+        assert_eq!(engine.board().garbage_rows(), 2);
+    }
+}
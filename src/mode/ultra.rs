@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use super::{format_time, parse_setting, unknown_setting, Mode, Timer};
+use crate::engine::Engine;
+
+// Score as much as possible before the time runs out.
+pub struct Ultra {
+    pub duration: Duration,
+    timer: Timer,
+}
+
+impl Ultra {
+    pub fn new(duration: Duration) -> Self {
+        Ultra {
+            duration,
+            timer: Timer::default(),
+        }
+    }
+
+    fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.timer.elapsed())
+    }
+}
+
+impl Mode for Ultra {
+    fn name(&self) -> &'static str {
+        "ultra"
+    }
+
+    fn start(&mut self, _engine: &mut Engine) {
+        self.timer = Timer::default();
+    }
+
+    fn update(&mut self, _engine: &mut Engine) -> Result<bool, String> {
+        self.timer.tick();
+        Ok(self.remaining().is_zero())
+    }
+
+    fn status(&self, _engine: &Engine) -> Vec<(String, String)> {
+        vec![("Time left".to_string(), format_time(self.remaining()))]
+    }
+
+    fn summary(&self, engine: &Engine) -> Vec<String> {
+        let minutes = self.timer.elapsed().as_secs_f64() / 60.0;
+        let per_minute = if minutes > 0.0 {
+            engine.points as f64 / minutes
+        } else {
+            0.0
+        };
+        vec![
+            format!("SCORE {}", engine.points),
+            format!("LINES {}", engine.rows_cleared),
+            format!("{:.0} POINTS PER MINUTE", per_minute),
+        ]
+    }
+
+    // Whole seconds left, rounded up so it reads 0:00 only at the end.
+    fn headline(&self, _engine: &Engine) -> Option<String> {
+        let seconds = self.remaining().as_millis().div_ceil(1000);
+        Some(format!("{}:{:02}", seconds / 60, seconds % 60))
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![("millis", self.duration.as_millis().to_string())]
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "millis" => self.duration = Duration::from_millis(parse_setting(name, value)?),
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+}
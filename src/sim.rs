@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::engine::piece::Direction;
+use crate::engine::Engine;
+use crate::input::{Frame, InputSource};
+
+// Same cadence as the SDL game loop.
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub max_pieces: Option<usize>,
+    pub max_time: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GameResult {
+    pub seed: u64,
+    pub pieces: usize,
+    pub lines: usize,
+    pub points: usize,
+    pub level: usize,
+    pub time: Duration,
+    pub topped_out: bool,
+}
+
+impl std::fmt::Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "seed={} pieces={} lines={} score={} level={} time={:.3}s result={}",
+            self.seed,
+            self.pieces,
+            self.lines,
+            self.points,
+            self.level,
+            self.time.as_secs_f64(),
+            if self.topped_out { "topout" } else { "limit" }
+        )
+    }
+}
+
+// Applies one frame of input and ticks the engine, then moves a simulated
+// clock on. Errors on top out; pausing is up to the caller.
+pub fn step(engine: &mut Engine, frame: Frame) -> Result<(), String> {
+    for direction in frame.moves {
+        engine.try_move(direction);
+    }
+    if frame.hard_drop {
+        engine.drop()?;
+    }
+    engine.tick(frame.soft_drop)?;
+    engine.clock.advance(FRAME);
+    Ok(())
+}
+
+// Runs one game frame by frame on a simulated clock.
+pub fn run(seed: u64, source: &mut dyn InputSource, limits: Limits) -> GameResult {
+    play(Engine::simulated(seed), seed, source, limits)
+}
+
+// Like run(), on an engine set up by the caller.
+pub fn play(
+    mut engine: Engine,
+    seed: u64,
+    source: &mut dyn InputSource,
+    limits: Limits,
+) -> GameResult {
+    let start = engine.clock.now();
+    engine.place_cursor();
+
+    let mut topped_out = false;
+    loop {
+        let elapsed = engine.clock.now() - start;
+        if limits.max_pieces.is_some_and(|max| engine.pieces >= max)
+            || limits.max_time.is_some_and(|max| elapsed >= max)
+        {
+            break;
+        }
+
+        let frame = source.frame(&engine);
+        if step(&mut engine, frame).is_err() {
+            topped_out = true;
+            break;
+        }
+    }
+
+    GameResult {
+        seed,
+        pieces: engine.pieces,
+        lines: engine.rows_cleared,
+        points: engine.points,
+        level: engine.level,
+        time: engine.clock.now() - start,
+        topped_out,
+    }
+}
+
+// Plays one line of moves per piece and hard drops, cycling through the
+// script.
+pub struct Script {
+    lines: Vec<Vec<Direction>>,
+    handled: Option<usize>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut moves = vec![];
+            for token in line.split_whitespace() {
+                moves.push(match token.to_ascii_uppercase().as_str() {
+                    "L" => Direction::LEFT,
+                    "R" => Direction::RIGHT,
+                    "CW" => Direction::CW,
+                    "CCW" => Direction::CCW,
+                    "180" => Direction::FLIP,
+                    _ => return Err(format!("line {}: unknown move {:?}", number + 1, token)),
+                });
+            }
+            lines.push(moves);
+        }
+        if lines.is_empty() {
+            return Err("script has no moves".to_string());
+        }
+        Ok(Script {
+            lines,
+            handled: None,
+        })
+    }
+}
+
+impl InputSource for Script {
+    fn frame(&mut self, engine: &Engine) -> Frame {
+        if engine.cursor.is_none() || self.handled == Some(engine.pieces) {
+            return Frame::default();
+        }
+        self.handled = Some(engine.pieces);
+        Frame {
+            moves: self.lines[engine.pieces % self.lines.len()].clone(),
+            hard_drop: true,
+            ..Frame::default()
+        }
+    }
+}
+
+// Rotates and shifts every piece at random before hard dropping it.
+pub struct RandomMoves {
+    rng: ChaCha8Rng,
+    handled: Option<usize>,
+}
+
+impl RandomMoves {
+    pub fn new(seed: u64) -> Self {
+        RandomMoves {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            handled: None,
+        }
+    }
+}
+
+impl InputSource for RandomMoves {
+    fn frame(&mut self, engine: &Engine) -> Frame {
+        if engine.cursor.is_none() || self.handled == Some(engine.pieces) {
+            return Frame::default();
+        }
+        self.handled = Some(engine.pieces);
+        let mut moves = vec![Direction::CW; self.rng.gen_range(0..4)];
+        let shift: isize = self.rng.gen_range(-5..=5);
+        let direction = if shift < 0 {
+            Direction::LEFT
+        } else {
+            Direction::RIGHT
+        };
+        moves.extend(std::iter::repeat_n(direction, shift.unsigned_abs()));
+        Frame {
+            moves,
+            hard_drop: true,
+            ..Frame::default()
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::Planner;
+use crate::engine::piece::{Kind, Rotation};
+use crate::engine::placement::Placement;
+use crate::engine::{reachable_placements, Board, Coordinate, Engine};
+
+// Tetris Bot Protocol: JSON messages, one per line, over the bot's stdio.
+
+const BOARD_ROWS: usize = 40;
+// Suggestions are waited for on the caller's thread, which in the game is
+// the render loop, so a slow bot stalls the screen for up to this long.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+// How long a bot gets to exit after being told to quit before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl From<Rotation> for Orientation {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::N => Orientation::North,
+            Rotation::E => Orientation::East,
+            Rotation::S => Orientation::South,
+            Rotation::W => Orientation::West,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub kind: Kind,
+    pub orientation: Orientation,
+    pub x: isize,
+    pub y: isize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Move {
+    pub location: Location,
+    #[serde(default = "no_spin")]
+    pub spin: String,
+}
+
+fn no_spin() -> String {
+    "none".to_string()
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FrontendMessage {
+    Rules {
+        randomizer: &'static str,
+    },
+    Start {
+        hold: Option<Kind>,
+        queue: Vec<Kind>,
+        combo: usize,
+        back_to_back: bool,
+        board: Vec<Vec<Option<char>>>,
+    },
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: Kind,
+    },
+    Stop,
+    Quit,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    Info { name: String, version: String },
+    Ready,
+    Error { reason: String },
+    Suggestion { moves: Vec<Move> },
+}
+
+// Minos around the piece's centre in the north orientation, y pointing up,
+// as the protocol defines them.
+fn north_offsets(kind: Kind) -> [(isize, isize); 4] {
+    match kind {
+        Kind::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+        Kind::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        Kind::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+        Kind::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+        Kind::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+        Kind::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+        Kind::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+    }
+}
+
+fn offsets(kind: Kind, orientation: Orientation) -> [(isize, isize); 4] {
+    let turns = match orientation {
+        Orientation::North => 0,
+        Orientation::East => 1,
+        Orientation::South => 2,
+        Orientation::West => 3,
+    };
+    let mut offsets = north_offsets(kind);
+    for _ in 0..turns {
+        for offset in offsets.iter_mut() {
+            *offset = (offset.1, -offset.0);
+        }
+    }
+    offsets
+}
+
+// Board cells covered by a protocol location, sorted like Placement::key().
+pub fn location_cells(location: &Location) -> Vec<(isize, isize)> {
+    let mut cells: Vec<(isize, isize)> = offsets(location.kind, location.orientation)
+        .iter()
+        .map(|(dx, dy)| (location.x + dx, Board::HEIGHT - 1 - (location.y + dy)))
+        .collect();
+    cells.sort();
+    cells
+}
+
+// The location the protocol would give a placement.
+pub fn placement_location(placement: &Placement) -> Location {
+    let orientation = Orientation::from(placement.piece.rotation);
+    let mut cells: Vec<(isize, isize)> = placement
+        .key()
+        .iter()
+        .map(|(x, y)| (*x, Board::HEIGHT - 1 - y))
+        .collect();
+    cells.sort();
+    let mut minos = offsets(placement.piece.kind, orientation);
+    minos.sort();
+    Location {
+        kind: placement.piece.kind,
+        orientation,
+        x: cells[0].0 - minos[0].0,
+        y: cells[0].1 - minos[0].1,
+    }
+}
+
+fn board_rows(board: &Board) -> Vec<Vec<Option<char>>> {
+    (0..BOARD_ROWS as isize)
+        .map(|row| {
+            let y = Board::HEIGHT - 1 - row;
+            (0..Board::WIDTH)
+                .map(|x| {
+                    let coord = Coordinate::new(x, y);
+                    if y < 0 || !board.filled(coord) {
+                        return None;
+                    }
+                    Some(match board.kind(coord) {
+                        Some(kind) => format!("{}", kind).chars().next().unwrap(),
+                        None => 'G',
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub struct TbpBot {
+    pub name: String,
+    child: Child,
+    messages: Receiver<BotMessage>,
+    // What the bot has been told: its queue, current piece first, and the
+    // board it expects once its last move has been played.
+    queue: VecDeque<Kind>,
+    board: Option<Vec<Vec<Option<char>>>>,
+    started: bool,
+}
+
+impl TbpBot {
+    pub fn spawn(command: &str) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("empty bot command")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}: {}", program, e))?;
+        let stdout = child.stdout.take().ok_or("bot has no stdout")?;
+
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                match serde_json::from_str::<BotMessage>(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    // Unknown messages are ignored, as the protocol asks.
+                    Err(_) => continue,
+                }
+            }
+        });
+
+        let mut bot = TbpBot {
+            name: String::new(),
+            child,
+            messages,
+            queue: VecDeque::new(),
+            board: None,
+            started: false,
+        };
+        match bot.receive()? {
+            BotMessage::Info { name, version } => bot.name = format!("{} {}", name, version),
+            other => return Err(format!("expected info, bot sent {:?}", other)),
+        }
+        bot.send(&FrontendMessage::Rules {
+            randomizer: "seven_bag",
+        })?;
+        match bot.receive()? {
+            BotMessage::Ready => Ok(bot),
+            BotMessage::Error { reason } => Err(format!("bot refused the rules: {}", reason)),
+            other => Err(format!("expected ready, bot sent {:?}", other)),
+        }
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> Result<(), String> {
+        let line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        let stdin = self.child.stdin.as_mut().ok_or("bot has no stdin")?;
+        writeln!(stdin, "{}", line).map_err(|e| e.to_string())?;
+        stdin.flush().map_err(|e| e.to_string())
+    }
+
+    fn receive(&mut self) -> Result<BotMessage, String> {
+        self.messages
+            .recv_timeout(REPLY_TIMEOUT)
+            .map_err(|e| match e {
+                RecvTimeoutError::Timeout => "bot did not answer in time".to_string(),
+                RecvTimeoutError::Disconnected => "bot exited".to_string(),
+            })
+    }
+
+    // Brings the bot in line with the engine, restarting it when anything
+    // it was told no longer holds, e.g. after a rejected suggestion.
+    fn sync(&mut self, engine: &Engine, current: Kind) -> Result<(), String> {
+        let expected: VecDeque<Kind> = std::iter::once(current)
+            .chain(engine.queue.iter().copied())
+            .collect();
+        let rows = board_rows(engine.board());
+        let known = self.queue.len() <= expected.len()
+            && self.queue.iter().zip(&expected).all(|(a, b)| a == b);
+
+        if known && self.board.as_ref() == Some(&rows) {
+            for kind in expected.iter().skip(self.queue.len()) {
+                self.send(&FrontendMessage::NewPiece { piece: *kind })?;
+            }
+        } else {
+            if self.started {
+                self.send(&FrontendMessage::Stop)?;
+            }
+            self.started = true;
+            self.send(&FrontendMessage::Start {
+                hold: None,
+                queue: expected.iter().copied().collect(),
+                combo: 0,
+                back_to_back: false,
+                board: rows,
+            })?;
+        }
+        self.queue = expected;
+        Ok(())
+    }
+
+    fn suggest(&mut self, engine: &Engine) -> Result<Option<Placement>, String> {
+        let cursor = match &engine.cursor {
+            Some(c) => *c,
+            None => return Ok(None),
+        };
+        self.sync(engine, cursor.kind)?;
+        self.send(&FrontendMessage::Suggest)?;
+        let moves = loop {
+            match self.receive()? {
+                BotMessage::Suggestion { moves } => break moves,
+                BotMessage::Error { reason } => return Err(reason),
+                _ => continue,
+            }
+        };
+
+        let placements = reachable_placements(engine.board(), &cursor);
+        for mv in moves {
+            if mv.location.kind != cursor.kind {
+                continue;
+            }
+            let cells = location_cells(&mv.location);
+            if let Some(placement) = placements.iter().find(|p| p.key() == cells) {
+                // Tell the bot where the piece really went, in case it named
+                // an equivalent location, e.g. an O in another orientation.
+                let location = placement_location(placement);
+                self.send(&FrontendMessage::Play {
+                    mv: Move {
+                        location,
+                        spin: mv.spin,
+                    },
+                })?;
+                let mut after = engine.board().clone();
+                after.place(&placement.piece)?;
+                self.board = Some(board_rows(&after));
+                self.queue.pop_front();
+                return Ok(Some(placement.clone()));
+            }
+        }
+        // Nothing we can play: make the next sync start the bot over.
+        self.board = None;
+        Ok(None)
+    }
+}
+
+impl Planner for TbpBot {
+    fn plan(&mut self, engine: &Engine) -> Option<Placement> {
+        match self.suggest(engine) {
+            Ok(placement) => placement,
+            Err(e) => {
+                eprintln!("tbp: {}", e);
+                self.board = None;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        // Closing stdin tells a bot that ignores quit that nothing more is
+        // coming; one that still hangs on is killed.
+        drop(self.child.stdin.take());
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while let Ok(None) = self.child.try_wait() {
+            if Instant::now() >= deadline {
+                let _ = self.child.kill();
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::piece::Piece;
+
+    #[test]
+    fn placement_locations_round_trip() {
+        let board = Board::from_rows(&[]);
+        for kind in Kind::ALL {
+            for placement in reachable_placements(&board, &Piece::spawn(kind)) {
+                let location = placement_location(&placement);
+                assert_eq!(location_cells(&location), placement.key(), "{:?}", location);
+            }
+        }
+    }
+
+    // A T on the floor of an empty board in each orientation, with its
+    // centre at x = 4 as the protocol places it.
+    #[test]
+    fn t_locations_match_the_protocol() {
+        let floor = Board::HEIGHT - 1;
+        let examples = [
+            (Orientation::North, 0, [(3, 0), (4, 0), (4, 1), (5, 0)]),
+            (Orientation::East, 1, [(4, 0), (4, 1), (4, 2), (5, 1)]),
+            (Orientation::South, 1, [(3, 1), (4, 0), (4, 1), (5, 1)]),
+            (Orientation::West, 1, [(3, 1), (4, 0), (4, 1), (4, 2)]),
+        ];
+        let placements = reachable_placements(&Board::from_rows(&[]), &Piece::spawn(Kind::T));
+        for (orientation, y, cells) in examples {
+            let location = Location {
+                kind: Kind::T,
+                orientation,
+                x: 4,
+                y,
+            };
+            let mut cells: Vec<(isize, isize)> =
+                cells.iter().map(|(x, y)| (*x, floor - y)).collect();
+            cells.sort();
+            assert_eq!(location_cells(&location), cells);
+            let placement = placements
+                .iter()
+                .find(|placement| placement.key() == cells)
+                .expect("T fits on the floor");
+            assert_eq!(placement_location(placement), location);
+        }
+    }
+}